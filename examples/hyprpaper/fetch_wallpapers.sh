@@ -37,9 +37,12 @@ find "$WALLPAPER_DIR" -type f \( -iname "*.jpg" -o -iname "*.jpeg" -o -iname "*.
         "type": "path",
         "value": "$file"
       },
+      "fields": {
+        "path": "$file"
+      },
       "action": {
         "action": "exec",
-        "cmd": "hyprctl hyprpaper preload \"${file}\" && hyprctl hyprpaper wallpaper \",${file}\""      
+        "cmd": "hyprctl hyprpaper preload {path} && hyprctl hyprpaper wallpaper ,{path}"
       }
     }
 EOF
@@ -0,0 +1,37 @@
+use pppicker::{Action, CustomInput, InputData, Item, Picker, SearchBar, Selection};
+
+fn main() -> anyhow::Result<()> {
+    let items = ["red", "green", "blue"]
+        .into_iter()
+        .map(|color| Item::new(color, color))
+        .collect();
+
+    let mut data = InputData::new("colors", items);
+    data.custom_input = Some(CustomInput {
+        action: Action::Print {
+            value: "{query}".to_string(),
+        },
+    });
+    data.search_bar = Some(SearchBar {
+        placeholder: "Pick a color...".to_string(),
+        ..SearchBar::default()
+    });
+    data.custom_keys = vec!["alt+c".to_string()];
+
+    match Picker::new(data).run()? {
+        Selection::Chosen { item, query, .. } => {
+            println!("chose {} (searched for '{}')", item.id, query)
+        }
+        Selection::Bound { key, item, .. } => println!("pressed {} on {}", key, item.id),
+        Selection::CustomKey { item, .. } => match item {
+            Some((_, item)) => println!("copy {}", item.id),
+            None => println!("nothing to copy"),
+        },
+        Selection::Marked { items, .. } => println!("chose {} colors", items.len()),
+        Selection::Custom { query } => println!("typed a new color '{}'", query),
+        Selection::NoMatch { query } => println!("nothing matched '{}'", query),
+        Selection::Cancelled => println!("cancelled"),
+    }
+
+    Ok(())
+}
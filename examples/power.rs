@@ -0,0 +1,11 @@
+use std::process::Command;
+
+fn main() -> anyhow::Result<()> {
+    let _ = Command::new("cargo")
+        .arg("run")
+        .arg("--")
+        .arg("./examples/example-power.toml")
+        .status()?;
+
+    Ok(())
+}
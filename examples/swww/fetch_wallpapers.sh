@@ -39,7 +39,7 @@ find "$WALLPAPER_DIR" -type f \( -iname "*.jpg" -o -iname "*.jpeg" -o -iname "*.
       },
       "action": {
         "action": "exec",
-        "cmd": "swww img \"${file}\""
+        "args": ["swww", "img", "$file"]
       }
     }
 EOF
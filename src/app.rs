@@ -1,43 +1,254 @@
-use std::sync::mpsc;
-use std::time::Duration;
+use std::fmt::Display;
+use std::process;
 
 use anyhow::{Context, Result};
-use gio::prelude::ApplicationExt;
-use gtk::Application;
-use gtk::prelude::*;
 
-use crate::core::load_css;
-use crate::{InputData, frontend, read_input};
+use crate::cli::{Command, HELP, Options, Source, parse_args};
+use crate::config::Config;
+use crate::core::{EXIT_CUSTOM_KEY, run_action, run_actions};
+use crate::data::{Action, Format, InputData, Item, Output};
+use crate::keys::bound_action;
+use crate::picker::{Picker, Selection};
+use crate::schema::schema;
+use crate::template::Placeholders;
+use crate::validate::{check, validate};
 
 pub fn run() -> Result<i32> {
+    let options = match parse_args()? {
+        Command::Run(options) => options,
+        Command::Schema => {
+            println!("{:#}", schema());
+            return Ok(0);
+        }
+        Command::Help => {
+            print!("{}", HELP);
+            return Ok(0);
+        }
+        Command::Version => {
+            println!("pppicker {}", env!("CARGO_PKG_VERSION"));
+            return Ok(0);
+        }
+    };
+
+    let config = Config::load()?;
+    let input = options.read_input()?;
+    let mut data = load(
+        &options.source,
+        &input.content,
+        input.format,
+        options.validate,
+    )?;
+    config.apply(&mut data);
+    options.apply(&mut data);
+
+    if options.validate {
+        eprintln!(
+            "{}: valid {} input ({} items)",
+            options.source,
+            input.format,
+            data.items.len()
+        );
+        return Ok(0);
+    }
+
+    let css = options.read_css()?;
+
     unsafe {
         std::env::set_var("GDK_BACKEND", "wayland");
     }
 
-    let input = read_input()?;
-    let (tx, rx) = mpsc::channel::<i32>();
-    let data: InputData = serde_json::from_str(&input).context("Invalid JSON input")?;
+    let exit_codes = data.exit_codes;
+    let default_action = data.default_action.clone();
+    let custom_input = data.custom_input.clone();
+    let multi_select = data.multi_select.clone();
+    let output = data.output.clone();
+    let bindings = data.bindings.clone();
+    let mut picker = Picker::new(data);
+    if let Some(css) = css {
+        picker = picker.css(css);
+    }
 
-    gtk::init().expect("Failed to initialize GTK");
+    let kept_default_action = default_action.clone();
+    let kept_output = output.clone();
+    picker = picker.keep_open(move |selection| {
+        keep_open(
+            selection,
+            &options,
+            input.format,
+            kept_default_action.as_ref(),
+            &kept_output,
+        )
+        .unwrap_or_else(|err| {
+            eprintln!("Error: {:?}", err);
+            None
+        })
+    });
 
-    load_css(&data.name);
+    // In multi-select mode a single chosen item is output like a marked one.
+    let selection = match (picker.run()?, &multi_select) {
+        (Selection::Chosen { index, item, query }, Some(_)) => Selection::Marked {
+            items: vec![(index, *item)],
+            query,
+        },
+        (selection, _) => selection,
+    };
 
-    let app = Application::builder()
-        .application_id("dev.pppicker")
-        .flags(gio::ApplicationFlags::FLAGS_NONE)
-        .build();
+    match selection {
+        Selection::Chosen { index, item, query } => run_action(
+            action_for(&item, default_action.as_ref())?,
+            &Placeholders {
+                item: Some((index, &item)),
+                query: &query,
+            },
+            &output,
+        ),
+        Selection::Marked { items, query } => {
+            let actions = items
+                .iter()
+                .map(|(index, item)| {
+                    let placeholders = Placeholders {
+                        item: Some((*index, item)),
+                        query: &query,
+                    };
+                    Ok((action_for(item, default_action.as_ref())?, placeholders))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let marked = multi_select.map(|multi| multi.output).unwrap_or_default();
+            run_actions(&actions, &output, marked)
+        }
+        Selection::Bound {
+            key,
+            index,
+            item,
+            query,
+        } => {
+            let action = bound_action(&item.bindings, &key)
+                .or_else(|| bound_action(&bindings, &key))
+                .with_context(|| format!("Item \"{}\" has no action for {}", item.id, key))?;
+            run_action(
+                action,
+                &Placeholders {
+                    item: Some((index, &item)),
+                    query: &query,
+                },
+                &output,
+            )
+        }
+        // Like rofi, a custom key prints the highlighted item, or the query.
+        Selection::CustomKey {
+            number,
+            item,
+            query,
+        } => {
+            let value = if item.is_some() { "{id}" } else { "{query}" };
+            run_action(
+                &Action::Print {
+                    value: value.to_string(),
+                },
+                &Placeholders {
+                    item: item.as_ref().map(|(index, item)| (*index, &**item)),
+                    query: &query,
+                },
+                &output,
+            )?;
+            Ok(EXIT_CUSTOM_KEY + number as i32)
+        }
+        Selection::Custom { query } => match &custom_input {
+            Some(custom_input) => run_action(
+                &custom_input.action,
+                &Placeholders {
+                    item: None,
+                    query: &query,
+                },
+                &output,
+            ),
+            None => Ok(exit_codes.no_match),
+        },
+        Selection::NoMatch { .. } => Ok(exit_codes.no_match),
+        Selection::Cancelled => Ok(exit_codes.cancel),
+    }
+}
 
-    let tx_ui = tx.clone();
+fn action_for<'a>(item: &'a Item, default_action: Option<&'a Action>) -> Result<&'a Action> {
+    item.action.as_ref().or(default_action).with_context(|| {
+        format!(
+            "Item \"{}\" has no action and the picker has no default-action",
+            item.id
+        )
+    })
+}
 
-    app.connect_activate(move |app| {
-        frontend::run_ui(app, data.clone(), tx_ui.clone());
-    });
+// A normal run only checks what the picker itself depends on. Missing files
+// and unknown keys are left to `validate`.
+fn load(source: &impl Display, content: &str, format: Format, thorough: bool) -> Result<InputData> {
+    let report = if thorough {
+        validate(content, format)
+    } else {
+        check(content, format)
+    };
 
-    app.run_with_args::<&str>(&[]);
+    for diagnostic in &report.diagnostics {
+        // A location continues the source as `file:line:column: `.
+        match diagnostic.location {
+            Some(_) => eprintln!("{}:{}", source, diagnostic),
+            None => eprintln!("{}: {}", source, diagnostic),
+        }
+    }
 
-    if let Ok(code) = rx.recv_timeout(Duration::from_millis(10)) {
-        std::process::exit(code);
-    } else {
-        anyhow::bail!("timeout while waiting for exit code");
+    let errors = report.has_errors();
+    report
+        .data
+        .filter(|_| !errors)
+        .with_context(|| format!("{}: invalid {} input", source, format))
+}
+
+// Runs the action of a chosen item while the picker stays open, and returns
+// the new items for a reload action.
+fn keep_open(
+    selection: &Selection,
+    options: &Options,
+    format: Format,
+    default_action: Option<&Action>,
+    output: &Output,
+) -> Result<Option<Vec<Item>>> {
+    let Selection::Chosen { index, item, query } = selection else {
+        return Ok(None);
+    };
+    let placeholders = Placeholders {
+        item: Some((*index, item)),
+        query,
+    };
+    let action = action_for(item, default_action)?;
+
+    if let Action::Reload { cmd } = placeholders.apply(action) {
+        let data = match cmd {
+            Some(cmd) => load(&cmd, &command_output(&cmd)?, format, false)?,
+            None => match &options.source {
+                Source::File(_) => {
+                    let input = options.read_input()?;
+                    load(&options.source, &input.content, input.format, false)?
+                }
+                Source::Stdin => anyhow::bail!(
+                    "Cannot reload input read from stdin, give the reload action a \"cmd\""
+                ),
+            },
+        };
+        return Ok(Some(data.items));
+    }
+
+    run_action(action, &placeholders, output)?;
+    Ok(None)
+}
+
+fn command_output(cmd: &str) -> Result<String> {
+    let output = process::Command::new("sh")
+        .arg("-c")
+        .arg(cmd)
+        .stderr(process::Stdio::inherit())
+        .output()
+        .with_context(|| format!("Failed to run reload command {}", cmd))?;
+    if !output.status.success() {
+        anyhow::bail!("Reload command {} failed with {}", cmd, output.status);
     }
+    String::from_utf8(output.stdout).context("Reload command printed invalid UTF-8")
 }
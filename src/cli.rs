@@ -1,23 +1,208 @@
 use std::{
-    env, fs,
+    env, fmt, fs,
     io::{self, IsTerminal, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use anyhow::Context;
 
-pub fn read_input() -> anyhow::Result<String> {
-    let mut args = env::args().skip(1);
+use crate::data::{Format, InputData, Layout, SearchBar, Separator};
 
-    if let Some(path) = args.next() {
-        read_from_file(PathBuf::from(path))
-    } else {
-        read_from_stdin()
+pub const HELP: &str = "\
+pppicker - a CSS-styled picker for Wayland
+
+Usage: pppicker [OPTIONS] [INPUT]
+       pppicker validate [OPTIONS] [INPUT]
+       pppicker schema
+
+Commands:
+  validate  Check the input and report every problem with its location, without opening a window
+  schema    Print the JSON Schema of the input format
+
+Arguments:
+  [INPUT]  Picker definition to read (JSON, TOML or dmenu lines), or '-' for stdin [default: -]
+
+Options:
+  -f, --format <FORMAT>  Input format: json, toml or dmenu [default: from extension or content]
+      --dmenu            Read newline-separated lines and print the chosen one (same as --format dmenu)
+  -l, --layout <LAYOUT>  Override the layout: list or grid
+  -c, --css <FILE>       Use this stylesheet instead of looking one up by name
+  -n, --name <NAME>      Override the picker name used for the CSS lookup
+  -p, --prompt <TEXT>    Show the search bar with this placeholder
+  -0, --null             End printed records with NUL instead of a newline, for xargs -0
+      --validate         Same as the validate command
+  -h, --help             Print help
+  -V, --version          Print version
+";
+
+#[derive(Debug)]
+pub enum Command {
+    Run(Options),
+    Schema,
+    Help,
+    Version,
+}
+
+#[derive(Debug, Default)]
+pub enum Source {
+    #[default]
+    Stdin,
+    File(PathBuf),
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub source: Source,
+    pub format: Option<Format>,
+    pub layout: Option<Layout>,
+    pub css: Option<PathBuf>,
+    pub name: Option<String>,
+    pub prompt: Option<String>,
+    pub null: bool,
+    pub validate: bool,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Stdin => write!(f, "<stdin>"),
+            Source::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+pub struct Input {
+    pub content: String,
+    pub format: Format,
+}
+
+pub fn parse_args() -> anyhow::Result<Command> {
+    parse_args_from(env::args().skip(1))
+}
+
+pub fn parse_args_from(args: impl IntoIterator<Item = String>) -> anyhow::Result<Command> {
+    let mut options = Options::default();
+    let mut source = None;
+    let mut args = args.into_iter().peekable();
+    let mut positional_only = false;
+
+    match args.peek().map(String::as_str) {
+        Some("validate") => {
+            args.next();
+            options.validate = true;
+        }
+        Some("schema") => {
+            args.next();
+            if let Some(arg) = args.next() {
+                anyhow::bail!("Unexpected argument '{}' for schema", arg);
+            }
+            return Ok(Command::Schema);
+        }
+        _ => {}
+    }
+
+    while let Some(arg) = args.next() {
+        if positional_only || arg == "-" || !arg.starts_with('-') {
+            if source.is_some() {
+                anyhow::bail!("Unexpected argument '{}'\n\n{}", arg, usage_hint());
+            }
+            source = Some(if arg == "-" {
+                Source::Stdin
+            } else {
+                Source::File(PathBuf::from(arg))
+            });
+            continue;
+        }
+
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (arg.as_str(), None),
+        };
+
+        let mut value = || {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .with_context(|| format!("Option '{}' requires a value", flag))
+        };
+
+        match flag {
+            "--" => positional_only = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-f" | "--format" => options.format = Some(value()?.parse()?),
+            "-l" | "--layout" => options.layout = Some(value()?.parse()?),
+            "-c" | "--css" => options.css = Some(PathBuf::from(value()?)),
+            "-n" | "--name" => options.name = Some(value()?),
+            "-p" | "--prompt" => options.prompt = Some(value()?),
+            "--dmenu" | "--null" | "--validate" if inline.is_some() => {
+                anyhow::bail!("Option '{}' does not take a value", flag)
+            }
+            "--dmenu" => options.format = Some(Format::Dmenu),
+            "-0" | "--null" => options.null = true,
+            "--validate" => options.validate = true,
+            _ => anyhow::bail!("Unknown option '{}'\n\n{}", flag, usage_hint()),
+        }
+    }
+
+    options.source = source.unwrap_or_default();
+    Ok(Command::Run(options))
+}
+
+fn usage_hint() -> &'static str {
+    "Usage: pppicker [OPTIONS] [INPUT]\n       pppicker validate [OPTIONS] [INPUT]\n       pppicker schema\nFor more information, try '--help'."
+}
+
+impl Options {
+    pub fn read_input(&self) -> anyhow::Result<Input> {
+        match &self.source {
+            Source::File(path) => {
+                let content = read_from_file(path)?;
+                let format = self
+                    .format
+                    .or_else(|| Format::from_path(path))
+                    .unwrap_or_else(|| Format::sniff(&content));
+                Ok(Input { content, format })
+            }
+            Source::Stdin => {
+                let content = read_from_stdin()?;
+                let format = self.format.unwrap_or_else(|| Format::sniff(&content));
+                Ok(Input { content, format })
+            }
+        }
+    }
+
+    // Command-line flags take precedence over the input document.
+    pub fn apply(&self, data: &mut InputData) {
+        if let Some(name) = &self.name {
+            data.name = name.clone();
+        }
+        if let Some(layout) = self.layout {
+            data.view.layout = layout;
+        }
+        if let Some(prompt) = &self.prompt {
+            data.search_bar
+                .get_or_insert_with(SearchBar::default)
+                .placeholder = prompt.clone();
+        }
+        if self.null {
+            data.output.separator = Separator::Nul;
+        }
+    }
+
+    pub fn read_css(&self) -> anyhow::Result<Option<String>> {
+        self.css
+            .as_deref()
+            .map(|path| {
+                fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read CSS file {}", path.display()))
+            })
+            .transpose()
     }
 }
 
-fn read_from_file(path: PathBuf) -> anyhow::Result<String> {
-    let input = fs::read_to_string(&path)
+fn read_from_file(path: &Path) -> anyhow::Result<String> {
+    let input = fs::read_to_string(path)
         .with_context(|| format!("Failed to read file {}", path.display()))?;
     tracing::info!("loaded content from: {}", path.display());
     Ok(input)
@@ -38,3 +223,90 @@ fn read_from_stdin() -> anyhow::Result<String> {
     tracing::info!("loaded content from: stdin");
     Ok(buffer)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<Command> {
+        parse_args_from(args.iter().map(|arg| arg.to_string()))
+    }
+
+    fn options(args: &[&str]) -> Options {
+        match parse(args).unwrap() {
+            Command::Run(options) => options,
+            other => panic!("expected options, got {:?}", other),
+        }
+    }
+
+    fn error(args: &[&str]) -> String {
+        parse(args).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn defaults_to_stdin() {
+        let options = options(&[]);
+        assert!(matches!(options.source, Source::Stdin));
+        assert!(!options.validate && !options.null);
+    }
+
+    #[test]
+    fn dash_is_stdin() {
+        assert!(matches!(options(&["-"]).source, Source::Stdin));
+        assert!(matches!(
+            options(&["-f", "toml", "-"]).source,
+            Source::Stdin
+        ));
+    }
+
+    #[test]
+    fn flags_with_separate_and_inline_values() {
+        let options = options(&[
+            "--format=toml",
+            "-l",
+            "grid",
+            "--name",
+            "walls",
+            "--prompt=Pick = one",
+            "-0",
+            "walls.toml",
+        ]);
+        assert_eq!(options.format, Some(Format::Toml));
+        assert!(matches!(options.layout, Some(Layout::Grid)));
+        assert_eq!(options.name.as_deref(), Some("walls"));
+        assert_eq!(options.prompt.as_deref(), Some("Pick = one"));
+        assert!(options.null);
+        assert!(matches!(options.source, Source::File(path) if path == Path::new("walls.toml")));
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let options = options(&["--", "--help"]);
+        assert!(matches!(options.source, Source::File(path) if path == Path::new("--help")));
+    }
+
+    #[test]
+    fn subcommands() {
+        assert!(options(&["validate", "in.json"]).validate);
+        assert!(matches!(parse(&["schema"]).unwrap(), Command::Schema));
+        assert!(matches!(parse(&["-h"]).unwrap(), Command::Help));
+        assert!(matches!(parse(&["--version"]).unwrap(), Command::Version));
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert!(error(&["--frobnicate"]).starts_with("Unknown option '--frobnicate'"));
+        assert!(error(&["--frobnicate=1"]).starts_with("Unknown option '--frobnicate'"));
+        assert_eq!(error(&["--name"]), "Option '--name' requires a value");
+        assert_eq!(
+            error(&["--null=yes"]),
+            "Option '--null' does not take a value"
+        );
+        assert!(error(&["a.json", "b.json"]).starts_with("Unexpected argument 'b.json'"));
+        assert_eq!(
+            error(&["schema", "x"]),
+            "Unexpected argument 'x' for schema"
+        );
+        assert!(parse(&["--layout", "table"]).is_err());
+    }
+}
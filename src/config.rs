@@ -0,0 +1,64 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::data::{InputData, KeyAction};
+use crate::keys::check_keys;
+
+/// Settings for every picker, from `~/.config/pppicker/config.toml`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Keys of built-in commands. A picker's own `keys` win per command.
+    #[serde(default)]
+    pub keys: BTreeMap<KeyAction, Vec<String>>,
+}
+
+impl Config {
+    pub fn path() -> Option<PathBuf> {
+        Some(
+            dirs_next::config_dir()?
+                .join("pppicker")
+                .join("config.toml"),
+        )
+    }
+
+    /// Reads the config file, or the defaults if there is none. Unknown
+    /// settings, commands and key names are an error.
+    pub fn load() -> anyhow::Result<Config> {
+        let Some(path) = Config::path() else {
+            return Ok(Config::default());
+        };
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Config::default()),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Failed to read config file {}", path.display()));
+            }
+        };
+
+        let config: Config = toml::from_str(&content)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        if let Some((action, err)) = check_keys(&config.keys).into_iter().next() {
+            anyhow::bail!(
+                "Invalid config file {}: keys.{}: {}",
+                path.display(),
+                action,
+                err
+            );
+        }
+        tracing::info!("loaded config from: {}", path.display());
+        Ok(config)
+    }
+
+    pub fn apply(&self, data: &mut InputData) {
+        for (action, keys) in &self.keys {
+            data.keys.entry(*action).or_insert_with(|| keys.clone());
+        }
+    }
+}
@@ -1,34 +1,243 @@
-use std::sync::mpsc;
+use std::io::{self, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, ExitStatus, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use gtk::{CssProvider, gdk::Display};
+use serde_json::Value;
+
+use crate::data::{Action, Exec, ExecMode, MarkedOutput, Output, OutputFormat, Separator};
+use crate::template::Placeholders;
+
+/// An item was chosen and its action ran.
+pub const EXIT_SELECTED: i32 = 0;
+/// The picker was closed without choosing anything. Default of `exit-codes.cancel`.
+pub const EXIT_CANCELLED: i32 = 1;
+/// Invalid arguments or input, or an action that failed to run.
+pub const EXIT_ERROR: i32 = 2;
+/// Enter was pressed while no item matched the search. Default of `exit-codes.no-match`.
+pub const EXIT_NO_MATCH: i32 = 3;
+/// The first of `custom-keys` was pressed; the next ones exit with 11, 12, ...
+pub const EXIT_CUSTOM_KEY: i32 = 10;
+/// A `wait` mode exec action ran into its timeout and was killed, as with timeout(1).
+pub const EXIT_TIMEOUT: i32 = 124;
+
+/// Expands the placeholders in the action of a chosen item, runs it and
+/// returns the exit status it maps to.
+pub fn run_action(
+    action: &Action,
+    placeholders: &Placeholders,
+    output: &Output,
+) -> anyhow::Result<i32> {
+    run_actions(&[(action, *placeholders)], output, MarkedOutput::Lines)
+}
+
+/// Runs the actions of several chosen items in order and returns the status
+/// of the first one that did not succeed, or 0.
+pub fn run_actions(
+    actions: &[(&Action, Placeholders)],
+    output: &Output,
+    marked: MarkedOutput,
+) -> anyhow::Result<i32> {
+    let mut records = Vec::new();
+    let mut status = EXIT_SELECTED;
+
+    for (action, placeholders) in actions {
+        let code = match placeholders.apply(action) {
+            Action::Print { value } => {
+                let record = record(value, placeholders, output)?;
+                match marked {
+                    MarkedOutput::Lines => print_record(&record, output.separator)?,
+                    MarkedOutput::Json => records.push(record),
+                }
+                EXIT_SELECTED
+            }
+            Action::Exec(exec) => execute(&exec)?,
+            Action::Exit { code } => code,
+            Action::Reload { .. } => {
+                anyhow::bail!("reload action only works while the picker stays open")
+            }
+        };
+        if status == EXIT_SELECTED {
+            status = code;
+        }
+    }
 
-use gtk::{CssProvider, Window, gdk::Display, prelude::GtkWindowExt};
+    if marked == MarkedOutput::Json {
+        print_record(&Value::Array(records), output.separator)?;
+    }
+    Ok(status)
+}
+
+/// What a print action writes, by the picker's `output.format`. Formats that
+/// need an item fall back to the value for custom input.
+fn record(value: String, placeholders: &Placeholders, output: &Output) -> anyhow::Result<Value> {
+    let Some((index, item)) = placeholders.item else {
+        return Ok(Value::String(value));
+    };
+    Ok(match output.format {
+        OutputFormat::Value => Value::String(value),
+        OutputFormat::Id => Value::String(item.id.clone()),
+        OutputFormat::Index => Value::from(index),
+        OutputFormat::Json => serde_json::to_value(item)?,
+        OutputFormat::Template => {
+            let template = output
+                .template
+                .as_deref()
+                .context("output format \"template\" needs a \"template\"")?;
+            Value::String(placeholders.expand(template))
+        }
+    })
+}
 
-use crate::data::Action;
+// Flushed right away, as the process exits without running destructors and
+// a record that ends in NUL would otherwise stay in the buffer.
+fn print_record(record: &Value, separator: Separator) -> anyhow::Result<()> {
+    let mut stdout = io::stdout().lock();
+    match record {
+        Value::String(value) => stdout.write_all(value.as_bytes())?,
+        other => write!(stdout, "{}", other)?,
+    }
+    stdout.write_all(match separator {
+        Separator::Newline => b"\n",
+        Separator::Nul => b"\0",
+    })?;
+    stdout.flush()?;
+    Ok(())
+}
+
+fn execute(exec: &Exec) -> anyhow::Result<i32> {
+    let command = command(exec)?;
+    match exec.mode {
+        ExecMode::Detach => detach(command, exec),
+        ExecMode::Wait => wait(command, exec),
+    }
+}
 
-pub fn handle_action(action: &Action, tx: &mpsc::Sender<i32>, window: &Window) {
-    match action {
-        Action::Print { value } => {
-            println!("{}", value);
-            let _ = tx.send(0);
-            window.close();
+fn command(exec: &Exec) -> anyhow::Result<Command> {
+    let mut command = match (&exec.cmd, exec.args.as_deref()) {
+        (Some(cmd), None) => {
+            let mut command = Command::new("sh");
+            command.arg("-c").arg(cmd);
+            command
         }
-        Action::Exec { cmd } => {
-            let _ = std::process::Command::new("sh").arg("-c").arg(cmd).spawn();
-            let _ = tx.send(0);
-            window.close();
+        (None, Some([program, args @ ..])) => {
+            let mut command = Command::new(program);
+            command.args(args);
+            command
         }
-        Action::Exit { code } => {
-            let _ = tx.send(*code);
-            window.close();
+        (Some(_), Some(_)) => anyhow::bail!("exec action has both \"cmd\" and \"args\""),
+        (None, _) => anyhow::bail!("exec action requires \"cmd\" or a non-empty \"args\""),
+    };
+
+    if exec.clear_env {
+        command.env_clear();
+    }
+    command.envs(&exec.env);
+    if let Some(cwd) = &exec.cwd {
+        command.current_dir(cwd);
+    }
+    Ok(command)
+}
+
+// Double fork: the intermediate child starts a new session, forks the command
+// and exits, and is reaped right here. The command is reparented to init, so it
+// leaves no zombie and gets no SIGHUP when pppicker's terminal goes away.
+fn detach(mut command: Command, exec: &Exec) -> anyhow::Result<i32> {
+    command.stdin(Stdio::null()).stdout(Stdio::null());
+    unsafe {
+        command.pre_exec(|| {
+            if libc::setsid() == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            match libc::fork() {
+                -1 => Err(io::Error::last_os_error()),
+                0 => Ok(()),
+                _ => libc::_exit(0),
+            }
+        });
+    }
+
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run {}", describe(exec)))?;
+    child
+        .wait()
+        .with_context(|| format!("Failed to detach {}", describe(exec)))?;
+    Ok(EXIT_SELECTED)
+}
+
+fn wait(mut command: Command, exec: &Exec) -> anyhow::Result<i32> {
+    if !exec.forward_stdout {
+        command.stdout(Stdio::null());
+    }
+    let timeout = exec
+        .timeout
+        .map(|seconds| {
+            Duration::try_from_secs_f64(seconds)
+                .with_context(|| format!("Invalid exec timeout {}", seconds))
+        })
+        .transpose()?;
+    if timeout.is_some() {
+        // Its own process group, so a timeout also kills whatever `sh -c` started.
+        command.process_group(0);
+    }
+
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run {}", describe(exec)))?;
+
+    let status = match timeout {
+        None => child.wait()?,
+        Some(timeout) => {
+            let deadline = Instant::now() + timeout;
+            loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if Instant::now() >= deadline {
+                    warn!(
+                        "{} timed out after {:?}, killing it",
+                        describe(exec),
+                        timeout
+                    );
+                    unsafe { libc::killpg(child.id() as libc::pid_t, libc::SIGKILL) };
+                    let _ = child.wait();
+                    return Ok(EXIT_TIMEOUT);
+                }
+                thread::sleep(Duration::from_millis(10));
+            }
         }
+    };
+
+    Ok(exit_code(status))
+}
+
+// Like a shell: the command's own code, or 128 + N when killed by signal N.
+fn exit_code(status: ExitStatus) -> i32 {
+    status
+        .code()
+        .or_else(|| status.signal().map(|signal| 128 + signal))
+        .unwrap_or(EXIT_ERROR)
+}
+
+fn describe(exec: &Exec) -> String {
+    match (&exec.cmd, &exec.args) {
+        (Some(cmd), _) => format!("'{}'", cmd),
+        (None, Some(args)) => format!("{:?}", args),
+        (None, None) => "exec action".to_string(),
     }
 }
 
 use tracing::{info, warn};
 
 #[cfg(not(debug_assertions))]
-pub fn load_css(name: &str) {
+pub fn load_css(name: &str, css: Option<String>) -> CssProvider {
     let provider = CssProvider::new();
-    let css = get_picker_css(name)
+    let css = css
+        .or_else(|| get_picker_css(name))
         .or_else(|| {
             warn!("No picker-specific CSS found for '{}'", name);
             get_style_css()
@@ -46,12 +255,14 @@ pub fn load_css(name: &str) {
         &provider,
         gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
+    provider
 }
 
 #[cfg(debug_assertions)]
-pub fn load_css(name: &str) {
+pub fn load_css(name: &str, css: Option<String>) -> CssProvider {
     let provider = CssProvider::new();
-    let css = get_picker_css(name)
+    let css = css
+        .or_else(|| get_picker_css(name))
         .or_else(|| {
             warn!("No picker-specific CSS found for '{}'", name);
             get_current_css()
@@ -73,6 +284,7 @@ pub fn load_css(name: &str) {
         &provider,
         gtk::STYLE_PROVIDER_PRIORITY_APPLICATION,
     );
+    provider
 }
 
 fn get_picker_css(name: &str) -> Option<String> {
@@ -157,6 +369,10 @@ const DEFAULT_CSS: &str = r#"
     color: #282828;
 }
 
+.picker-row.picker-marked {
+    box-shadow: inset 4px 0 0 #b8bb26;
+}
+
 .picker-row:selected:hover {
     background-color: #fabd2f;
 }
@@ -196,6 +412,10 @@ const DEFAULT_CSS: &str = r#"
     color: #282828;
 }
 
+.picker-grid-item.picker-marked {
+    box-shadow: inset 0 -4px 0 #b8bb26;
+}
+
 .picker-grid-item:selected:hover {
     background-color: #fabd2f;
 }
@@ -210,6 +430,17 @@ const DEFAULT_CSS: &str = r#"
     margin-top: 8px;
 }
 
+/* Descriptions */
+.picker-description {
+    font-size: 11px;
+    color: #a89984;
+}
+
+.picker-row:selected .picker-description,
+.picker-grid-item:selected .picker-description {
+    color: #3c3836;
+}
+
 /* Icons */
 .picker-icon {
     margin-right: 8px;
@@ -1,28 +1,325 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
-use serde::Deserialize;
+use anyhow::Context;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize)]
+use crate::core::{EXIT_CANCELLED, EXIT_NO_MATCH};
+use crate::validate::{Diagnostic, Severity};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Toml,
+    Dmenu,
+}
+
+impl Format {
+    pub fn from_path(path: &Path) -> Option<Format> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(Format::Json),
+            "toml" => Some(Format::Toml),
+            _ => None,
+        }
+    }
+
+    // An input document is always a table, so JSON has to open with `{`.
+    pub fn sniff(input: &str) -> Format {
+        if input
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with('{')
+        {
+            Format::Json
+        } else {
+            Format::Toml
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(Format::Json),
+            "toml" => Ok(Format::Toml),
+            "dmenu" => Ok(Format::Dmenu),
+            other => anyhow::bail!(
+                "Unknown input format '{}' (expected json, toml or dmenu)",
+                other
+            ),
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Format::Json => write!(f, "JSON"),
+            Format::Toml => write!(f, "TOML"),
+            Format::Dmenu => write!(f, "dmenu"),
+        }
+    }
+}
+
+/// Picker definition read by pppicker from a file or stdin.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[schemars(title = "pppicker input")]
 pub struct InputData {
+    /// Picker name, used to look up `~/.config/pppicker/{name}.css`.
     pub name: String,
+    /// Entries shown in the picker, in input order.
     pub items: Vec<Item>,
     #[serde(default)]
     pub view: View,
+    /// Shows a search bar that filters the items.
     #[serde(default, rename = "search-bar")]
     pub search_bar: Option<SearchBar>,
+    #[serde(default, rename = "exit-codes")]
+    pub exit_codes: ExitCodes,
+    /// Action of items that do not have their own.
+    #[serde(default, rename = "default-action")]
+    pub default_action: Option<Action>,
+    #[serde(default, rename = "custom-input")]
+    pub custom_input: Option<CustomInput>,
+    #[serde(default, rename = "multi-select")]
+    pub multi_select: Option<MultiSelect>,
+    #[serde(default)]
+    pub output: Output,
+    /// Actions of items that do not bind the key themselves.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Action>,
+    /// Keys that end the picker with exit status 10, 11, ... in list order.
+    #[serde(default, rename = "custom-keys")]
+    #[schemars(length(max = crate::validate::MAX_CUSTOM_KEYS))]
+    pub custom_keys: Vec<String>,
+    /// Keys of built-in commands, replacing the defaults per command.
+    #[serde(default)]
+    #[schemars(schema_with = "crate::schema::keys")]
+    pub keys: BTreeMap<KeyAction, Vec<String>>,
+}
+
+impl InputData {
+    pub fn new(name: impl Into<String>, items: Vec<Item>) -> InputData {
+        InputData {
+            name: name.into(),
+            items,
+            view: View::default(),
+            search_bar: None,
+            exit_codes: ExitCodes::default(),
+            default_action: None,
+            custom_input: None,
+            multi_select: None,
+            output: Output::default(),
+            bindings: BTreeMap::new(),
+            custom_keys: Vec::new(),
+            keys: BTreeMap::new(),
+        }
+    }
+
+    /// Parses and validates `input`, failing with every error `validate` finds.
+    pub fn parse(input: &str, format: Format) -> anyhow::Result<InputData> {
+        let report = crate::validate::validate(input, format);
+        let errors: Vec<String> = report
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(Diagnostic::to_string)
+            .collect();
+        if !errors.is_empty() {
+            anyhow::bail!("Invalid {} input:\n{}", format, errors.join("\n"));
+        }
+        report
+            .data
+            .with_context(|| format!("Invalid {} input", format))
+    }
+
+    // dmenu compatible input: one item per non-empty line, printed when chosen.
+    // Text that matches no line is printed as typed.
+    pub fn from_lines(input: &str) -> InputData {
+        let items = input
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(index, line)| Item::new(index.to_string(), line))
+            .collect();
+
+        InputData {
+            search_bar: Some(SearchBar::default()),
+            default_action: Some(Action::Print {
+                value: "{label}".to_string(),
+            }),
+            custom_input: Some(CustomInput {
+                action: Action::Print {
+                    value: "{query}".to_string(),
+                },
+            }),
+            ..InputData::new("dmenu", items)
+        }
+    }
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Default, JsonSchema)]
 pub struct SearchBar {
     pub placeholder: String,
+    /// Pango span attributes for matched characters, bold and underlined if not set.
+    pub highlight: Option<String>,
+    #[serde(default)]
+    pub weights: SearchWeights,
+}
+
+/// Score multipliers per searched field. 0 excludes the field.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+#[serde(default)]
+pub struct SearchWeights {
+    pub label: f64,
+    pub keywords: f64,
+    pub description: f64,
+    pub id: f64,
+}
+
+impl Default for SearchWeights {
+    fn default() -> Self {
+        Self {
+            label: 1.0,
+            keywords: 0.8,
+            description: 0.6,
+            id: 0.5,
+        }
+    }
+}
+
+/// Lets Enter submit the search text itself when no item matches, and
+/// Shift+Enter at any time.
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+pub struct CustomInput {
+    /// Run with only the `{query}` placeholder set.
+    pub action: Action,
+}
+
+/// Lets several items be marked and chosen at once.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct MultiSelect {
+    #[serde(default)]
+    pub output: MarkedOutput,
+}
+
+/// How the print records of the marked items are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum MarkedOutput {
+    /// One record per item, each followed by the separator.
+    #[default]
+    Lines,
+    /// A single JSON array of all records.
+    Json,
+}
+
+/// Built-in commands that keys are bound to.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, JsonSchema,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum KeyAction {
+    /// Choose the highlighted or marked items.
+    Accept,
+    /// Submit the search text with `custom-input`, otherwise like `accept`.
+    SubmitQuery,
+    Cancel,
+    ClearQuery,
+    /// Delete the character before the text cursor.
+    DeleteChar,
+    /// Delete the word before the text cursor.
+    DeleteWord,
+    ToggleMark,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    First,
+    Last,
+}
+
+// The kebab-case name used in `keys`.
+impl std::fmt::Display for KeyAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match serde_json::to_value(self) {
+            Ok(serde_json::Value::String(name)) => write!(f, "{}", name),
+            _ => Err(std::fmt::Error),
+        }
+    }
+}
+
+/// What print actions write for a chosen item.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+pub struct Output {
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// Used with `format: template`, expanded like an action.
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub separator: Separator,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// The value of the print action.
+    #[default]
+    Value,
+    Id,
+    Index,
+    /// The whole item as a JSON object.
+    Json,
+    /// The `template` string.
+    Template,
+}
+
+/// Written after each printed record.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Separator {
+    #[default]
+    Newline,
+    /// A NUL byte, for `xargs -0`.
+    Nul,
+}
+
+/// Exit statuses for outcomes where no action runs.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct ExitCodes {
+    /// Picker closed without a choice, e.g. with Escape.
+    pub cancel: i32,
+    /// Enter pressed while no item matches the search.
+    pub no_match: i32,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+impl Default for ExitCodes {
+    fn default() -> Self {
+        Self {
+            cancel: EXIT_CANCELLED,
+            no_match: EXIT_NO_MATCH,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, Default, JsonSchema)]
+#[serde(default)]
 pub struct View {
     pub layout: Layout,
+    /// Hides the description line of all items.
+    #[serde(rename = "hide-description")]
+    pub hide_description: bool,
 }
 
-#[derive(Debug, Clone, Deserialize, Copy, Default)]
+#[derive(Debug, Clone, Deserialize, Serialize, Copy, Default, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Layout {
     Grid,
@@ -30,25 +327,179 @@ pub enum Layout {
     List,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl FromStr for Layout {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "grid" => Ok(Layout::Grid),
+            "list" => Ok(Layout::List),
+            other => anyhow::bail!("Unknown layout '{}' (expected list or grid)", other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 pub struct Item {
     pub id: String,
     pub label: String,
+    /// Second, smaller line shown below the label.
+    pub description: Option<String>,
+    /// Extra search terms, e.g. aliases.
+    #[serde(default)]
+    pub keywords: Vec<String>,
     pub icon: Option<Icon>,
-    pub action: Action,
+    /// Extra values for `{name}` placeholders in the action.
+    #[serde(default)]
+    pub fields: BTreeMap<String, String>,
+    /// Falls back to the picker's `default-action` when not set.
+    pub action: Option<Action>,
+    /// Runs the action without closing the picker.
+    #[serde(default, rename = "keep-open")]
+    pub keep_open: bool,
+    /// Alternate actions by key, like `ctrl+Return` or `alt+1`.
+    #[serde(default)]
+    pub bindings: BTreeMap<String, Action>,
+}
+
+impl Item {
+    pub fn new(id: impl Into<String>, label: impl Into<String>) -> Item {
+        Item {
+            id: id.into(),
+            label: label.into(),
+            description: None,
+            keywords: Vec::new(),
+            icon: None,
+            fields: BTreeMap::new(),
+            action: None,
+            keep_open: false,
+            bindings: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_action(mut self, action: Action) -> Item {
+        self.action = Some(action);
+        self
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "action", rename_all = "kebab-case")]
 pub enum Action {
-    Exec { cmd: String },
+    /// Spawns a command, given as a shell command line or as a program with arguments.
+    Exec(Exec),
+    /// Prints `value` to stdout.
     Print { value: String },
+    /// Exits with status `code`.
     Exit { code: i32 },
+    /// Reads the items again, from the input file or from the output of
+    /// `cmd`, and keeps the picker open.
+    Reload {
+        #[serde(default)]
+        cmd: Option<String>,
+    },
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// A command to spawn. Exactly one of `cmd` and `args` is set.
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+#[schemars(extend("oneOf" = [
+    { "required": ["cmd"], "properties": { "cmd": { "type": "string" } } },
+    { "required": ["args"], "properties": { "args": { "type": "array" } } },
+]))]
+pub struct Exec {
+    /// Shell command line, run with `sh -c`.
+    pub cmd: Option<String>,
+    /// Program and its arguments, run without a shell.
+    #[schemars(length(min = 1))]
+    pub args: Option<Vec<String>>,
+    /// Working directory of the command.
+    pub cwd: Option<PathBuf>,
+    /// Environment variables to set for the command.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Start from an empty environment instead of inheriting pppicker's.
+    #[serde(default)]
+    pub clear_env: bool,
+    #[serde(default)]
+    pub mode: ExecMode,
+    /// Seconds to wait in `wait` mode before the command is killed.
+    #[schemars(extend("exclusiveMinimum" = 0))]
+    pub timeout: Option<f64>,
+    /// In `wait` mode, pass the command's stdout through instead of discarding it.
+    #[serde(default)]
+    pub forward_stdout: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecMode {
+    /// Start the command in its own session and exit right away.
+    #[default]
+    Detach,
+    /// Wait for the command and exit with its status.
+    Wait,
+}
+
+impl Exec {
+    pub fn shell(cmd: impl Into<String>) -> Exec {
+        Exec {
+            cmd: Some(cmd.into()),
+            ..Exec::default()
+        }
+    }
+
+    pub fn argv<I, S>(args: I) -> Exec
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Exec {
+            args: Some(args.into_iter().map(Into::into).collect()),
+            ..Exec::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(tag = "type", content = "value", rename_all = "lowercase")]
 pub enum Icon {
+    /// A character or emoji.
     Unicode(String),
+    /// An image file.
     Path(PathBuf),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reports_validation_errors() {
+        let input = r#"{"name": "x", "items": [{"id": "a", "label": "A"}]}"#;
+        let err = InputData::parse(input, Format::Json).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Invalid JSON input:\n1:25: error: items[0] (id \"a\"): no \"action\" and the picker has no \"default-action\""
+        );
+
+        let input =
+            "name = \"x\"\ndefault-action = { action = \"print\", value = \"{id}\" }\nitems = []\n";
+        assert!(InputData::parse(input, Format::Toml).is_ok());
+    }
+
+    #[test]
+    fn view_fields_are_optional() {
+        let data: InputData = serde_json::from_str(
+            r#"{"name": "x", "items": [], "view": {"hide-description": true}}"#,
+        )
+        .unwrap();
+        assert!(data.view.hide_description);
+        assert!(matches!(data.view.layout, Layout::List));
+
+        let data: InputData =
+            toml::from_str("name = \"x\"\nitems = []\n\n[view]\nlayout = \"grid\"\n").unwrap();
+        assert!(!data.view.hide_description);
+        assert!(matches!(data.view.layout, Layout::Grid));
+    }
+}
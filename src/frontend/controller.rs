@@ -0,0 +1,464 @@
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use gtk::gdk::{Key, ModifierType};
+use gtk::prelude::*;
+
+use crate::data::{KeyAction, Layout};
+use crate::frontend::model::{ItemModel, scroll_to};
+use crate::frontend::{Restore, Session, create_scrolled, grid, highlight_attributes, list};
+use crate::keys::{Binding, Bindings, Keymap};
+use crate::matcher::{FilterState, QueryChange};
+use crate::picker::Selection;
+
+/// How a layout shows the items: the cells of its view, and how the
+/// highlight moves between them. Items are passed around by their index in
+/// the input; everything else is up to the [`Controller`].
+pub(super) trait Renderer {
+    fn model(&self) -> &ItemModel;
+
+    /// The list or grid view, shown below the search entry.
+    fn view(&self) -> &gtk::Widget;
+
+    fn move_cursor(&self, step: gtk::MovementStep, count: i32);
+
+    /// Whether Left and Right move between items. Otherwise they move the
+    /// text cursor in the search entry.
+    fn has_columns(&self) -> bool;
+
+    fn selected(&self) -> Option<usize> {
+        self.model().selected()
+    }
+
+    /// Highlights the item, or nothing with `None`.
+    fn select(&self, index: Option<usize>) {
+        if let Some(position) = self.model().select(index) {
+            scroll_to(self.view(), position);
+        }
+    }
+
+    fn set_marked(&self, index: usize, marked: bool) {
+        self.model().set_marked(index, marked);
+    }
+
+    /// Shows only the items visible in `state`, in its order, with matched
+    /// characters of the labels in `highlight` span attributes.
+    fn set_filter(&self, state: Rc<RefCell<FilterState>>, highlight: String) {
+        self.model().set_filter(state, highlight);
+    }
+
+    /// Applies a changed query of the filter state.
+    fn refilter(&self, change: QueryChange) {
+        self.model().refilter(change);
+    }
+
+    fn connect_activated(&self, callback: Box<dyn Fn(usize)>) {
+        self.model().connect_activated(callback);
+    }
+}
+
+const CONTROLLER_KEY: &str = "picker-controller";
+
+/// Builds the picker content for the session's layout.
+pub(super) fn create_picker(session: &Session, restore: Option<&Restore>) -> gtk::Box {
+    let data = &session.data;
+    let renderer: Box<dyn Renderer> = match data.view.layout {
+        Layout::List => Box::new(list::ListRenderer::new(Rc::clone(data))),
+        Layout::Grid => Box::new(grid::GridRenderer::new(Rc::clone(data))),
+    };
+
+    let entry = data.search_bar.as_ref().map(|config| {
+        let entry = gtk::SearchEntry::builder()
+            .placeholder_text(&config.placeholder)
+            .build();
+        entry.add_css_class("picker-search");
+        entry
+    });
+    let state = data
+        .search_bar
+        .as_ref()
+        .map(|config| Rc::new(RefCell::new(FilterState::new(&data.items, config.weights))));
+
+    let controller = Rc::new(Controller {
+        session: session.clone(),
+        renderer,
+        entry,
+        state,
+        custom_input: data.custom_input.is_some(),
+        multi_select: data.multi_select.is_some(),
+        bindings: Bindings::new(data),
+        keymap: Keymap::new(&data.keys),
+        marked: RefCell::default(),
+        reselect: RefCell::default(),
+        composing: Cell::default(),
+    });
+
+    let container = gtk::Box::new(gtk::Orientation::Vertical, 0);
+    if let (Some(entry), Some(state), Some(config)) =
+        (&controller.entry, &controller.state, &data.search_bar)
+    {
+        controller
+            .renderer
+            .set_filter(Rc::clone(state), highlight_attributes(config));
+        controller.setup_search(entry);
+        container.append(entry);
+    }
+    container.append(&create_scrolled(controller.renderer.view()));
+    controller.setup_activation();
+    controller.setup_keyboard(&container);
+
+    if let Some(restore) = restore {
+        controller.restore(restore);
+    }
+
+    // Handlers only hold weak references, so the controller goes away with
+    // the content, e.g. when a reload replaces it.
+    unsafe { container.set_data(CONTROLLER_KEY, controller) };
+
+    container
+}
+
+/// Items, query, selection and keys of a shown picker, whatever its layout.
+pub(super) struct Controller {
+    session: Session,
+    renderer: Box<dyn Renderer>,
+    entry: Option<gtk::SearchEntry>,
+    /// Query and matches of the search entry, if there is one.
+    state: Option<Rc<RefCell<FilterState>>>,
+    custom_input: bool,
+    multi_select: bool,
+    bindings: Bindings,
+    keymap: Keymap,
+    /// Items marked in multi-select mode, in the order they were marked.
+    marked: RefCell<Vec<usize>>,
+    /// Item id to select once a restored query has been applied.
+    reselect: RefCell<Option<String>>,
+    /// Whether the input method is composing text in the search entry.
+    composing: Cell<bool>,
+}
+
+impl Controller {
+    fn setup_search(self: &Rc<Self>, entry: &gtk::SearchEntry) {
+        let controller = Rc::downgrade(self);
+        entry.connect_search_changed(move |_| {
+            if let Some(controller) = controller.upgrade() {
+                controller.apply_query();
+            }
+        });
+    }
+
+    /// Filters the items by the search text, unless that is done already. The
+    /// entry reports changes only after a short delay, so keys call this too
+    /// and never act on the items of an older query.
+    fn apply_query(&self) {
+        let (Some(entry), Some(state)) = (&self.entry, &self.state) else {
+            return;
+        };
+        let query = entry.text();
+        if state.borrow().query() == query.as_str() {
+            return;
+        }
+        let change = state.borrow_mut().set_query(query.as_str());
+        self.renderer.refilter(change);
+        let state = state.borrow();
+
+        // Select the best match, unless a reload asked for an item. With no
+        // match, clear the selection so Enter reports it.
+        let items = &self.session.data.items;
+        let reselect = self
+            .reselect
+            .take()
+            .and_then(|id| items.iter().position(|item| item.id == id))
+            .filter(|&index| state.is_visible(index));
+        self.renderer.select(reselect.or_else(|| state.best()));
+    }
+
+    fn setup_activation(self: &Rc<Self>) {
+        let controller = Rc::downgrade(self);
+        self.renderer.connect_activated(Box::new(move |index| {
+            let Some(controller) = controller.upgrade() else {
+                return;
+            };
+            let selection = controller
+                .marked_selection()
+                .unwrap_or_else(|| controller.choose(index));
+            controller.session.finish(selection);
+        }));
+    }
+
+    fn setup_keyboard(self: &Rc<Self>, container: &gtk::Box) {
+        // Commands win over the list or grid in the capture phase. The search
+        // entry and its input method see most keys first, and those it leaves
+        // alone reach the commands in the bubble phase.
+        for phase in [
+            gtk::PropagationPhase::Capture,
+            gtk::PropagationPhase::Bubble,
+        ] {
+            let key_controller = gtk::EventControllerKey::new();
+            key_controller.set_propagation_phase(phase);
+            let controller = Rc::downgrade(self);
+            key_controller.connect_key_pressed(move |_, key, _keycode, modifiers| match controller
+                .upgrade()
+            {
+                Some(controller)
+                    if phase == gtk::PropagationPhase::Bubble
+                        || controller.captures(key, modifiers) =>
+                {
+                    controller.key_pressed(key, modifiers)
+                }
+                _ => glib::Propagation::Proceed,
+            });
+            container.add_controller(key_controller);
+        }
+
+        let Some(entry) = &self.entry else {
+            return;
+        };
+        // Typing while an item has focus still goes to the search.
+        entry.set_key_capture_widget(Some(container));
+
+        // The entry takes Enter and Escape for these signals.
+        let controller = Rc::downgrade(self);
+        entry.connect_activate(move |_| {
+            if let Some(controller) = controller.upgrade() {
+                controller.key_pressed(Key::Return, ModifierType::empty());
+            }
+        });
+        let controller = Rc::downgrade(self);
+        entry.connect_stop_search(move |_| {
+            if let Some(controller) = controller.upgrade() {
+                controller.key_pressed(Key::Escape, ModifierType::empty());
+            }
+        });
+
+        if let Some(text) = entry_text(entry) {
+            let controller = Rc::downgrade(self);
+            text.connect_local("preedit-changed", false, move |values| {
+                if let Some(controller) = controller.upgrade() {
+                    let preedit = values.get(1).and_then(|value| value.get::<String>().ok());
+                    controller
+                        .composing
+                        .set(preedit.is_some_and(|preedit| !preedit.is_empty()));
+                }
+                None
+            });
+        }
+    }
+
+    fn restore(&self, restore: &Restore) {
+        match &self.entry {
+            Some(entry) if !restore.query.is_empty() => {
+                self.reselect.replace(Some(restore.selected.clone()));
+                entry.set_text(&restore.query);
+            }
+            _ => {
+                let items = &self.session.data.items;
+                if let Some(index) = items.iter().position(|item| item.id == restore.selected) {
+                    self.renderer.select(Some(index));
+                }
+            }
+        }
+    }
+
+    /// Whether a command runs before the focused widget sees the key. The
+    /// search entry keeps its default editing keys, and while the input method
+    /// composes text it gets every key. Keys from `keys`, `bindings` and
+    /// `custom-keys`, and Left and Right in a grid, win over it otherwise.
+    fn captures(&self, key: Key, modifiers: ModifierType) -> bool {
+        if !self
+            .entry
+            .as_ref()
+            .and_then(entry_text)
+            .is_some_and(|text| text.has_focus())
+        {
+            return true;
+        }
+        if self.composing.get() {
+            return false;
+        }
+        self.bindings.lookup(key, modifiers).is_some()
+            || match self.keymap.lookup(key, modifiers, true) {
+                Some(KeyAction::Left | KeyAction::Right) => self.renderer.has_columns(),
+                Some(command) => self.keymap.is_configured(command),
+                None => false,
+            }
+    }
+
+    fn key_pressed(&self, key: Key, modifiers: ModifierType) -> glib::Propagation {
+        self.apply_query();
+        if let Some(selection) = self.key_selection(key, modifiers) {
+            self.session.finish(selection);
+            return glib::Propagation::Stop;
+        }
+
+        match self.keymap.lookup(key, modifiers, self.entry.is_some()) {
+            Some(command @ (KeyAction::Accept | KeyAction::SubmitQuery)) => {
+                self.accept(self.custom_input && command == KeyAction::SubmitQuery);
+            }
+            Some(KeyAction::ToggleMark) if self.multi_select => {
+                if let Some(index) = self.renderer.selected() {
+                    self.toggle_mark(index);
+                }
+            }
+            Some(KeyAction::Cancel) => {
+                if let Some(app) = self.session.window.application() {
+                    app.quit();
+                }
+                self.session.finish(Selection::Cancelled);
+            }
+            Some(
+                command @ (KeyAction::ClearQuery | KeyAction::DeleteChar | KeyAction::DeleteWord),
+            ) => {
+                if let Some(entry) = &self.entry {
+                    edit_query(entry, command);
+                }
+            }
+            Some(KeyAction::Left | KeyAction::Right) if !self.renderer.has_columns() => {
+                return glib::Propagation::Proceed;
+            }
+            Some(command) => match movement(command) {
+                Some((step, count)) => {
+                    self.renderer.move_cursor(step, count);
+                }
+                None => return glib::Propagation::Proceed,
+            },
+            // Everything else is text for the search entry.
+            None => return glib::Propagation::Proceed,
+        }
+        glib::Propagation::Stop
+    }
+
+    /// Enter chooses the marked items, else the highlighted one. With
+    /// `submit`, or when nothing matches, it submits the query instead.
+    fn accept(&self, submit: bool) {
+        let selection = if submit {
+            self.unmatched()
+        } else if let Some(selection) = self.marked_selection() {
+            selection
+        } else {
+            match self.renderer.selected() {
+                Some(index) => self.choose(index),
+                None => self.unmatched(),
+            }
+        };
+        self.session.finish(selection);
+    }
+
+    fn query(&self) -> String {
+        self.entry
+            .as_ref()
+            .map(|entry| entry.text().to_string())
+            .unwrap_or_default()
+    }
+
+    fn choose(&self, index: usize) -> Selection {
+        Selection::Chosen {
+            index,
+            item: Box::new(self.session.data.items[index].clone()),
+            query: self.query(),
+        }
+    }
+
+    // Enter without a chosen item submits the query itself when custom input is on.
+    fn unmatched(&self) -> Selection {
+        let query = self.query();
+        if self.custom_input && !query.is_empty() {
+            Selection::Custom { query }
+        } else {
+            Selection::NoMatch { query }
+        }
+    }
+
+    /// The selection for a key from `bindings` or `custom-keys`. `None` when
+    /// the key is not one of them, or not bound for the highlighted item.
+    fn key_selection(&self, key: Key, modifiers: ModifierType) -> Option<Selection> {
+        let items = &self.session.data.items;
+        let item = self
+            .renderer
+            .selected()
+            .and_then(|index| Some((index, items.get(index)?)));
+        match self.bindings.lookup(key, modifiers)? {
+            Binding::Custom(number) => Some(Selection::CustomKey {
+                number,
+                item: item.map(|(index, item)| (index, Box::new(item.clone()))),
+                query: self.query(),
+            }),
+            Binding::Action(key) => {
+                let (index, item) = item.filter(|(_, item)| self.bindings.binds(item, &key))?;
+                Some(Selection::Bound {
+                    key,
+                    index,
+                    item: Box::new(item.clone()),
+                    query: self.query(),
+                })
+            }
+        }
+    }
+
+    fn toggle_mark(&self, index: usize) {
+        let mut marked = self.marked.borrow_mut();
+        match marked.iter().position(|&other| other == index) {
+            Some(position) => {
+                marked.remove(position);
+                self.renderer.set_marked(index, false);
+            }
+            None => {
+                marked.push(index);
+                self.renderer.set_marked(index, true);
+            }
+        }
+    }
+
+    fn marked_selection(&self) -> Option<Selection> {
+        let marked = self.marked.borrow();
+        if marked.is_empty() {
+            return None;
+        }
+        let items = &self.session.data.items;
+        Some(Selection::Marked {
+            items: marked
+                .iter()
+                .filter_map(|&index| Some((index, items.get(index)?.clone())))
+                .collect(),
+            query: self.query(),
+        })
+    }
+}
+
+/// How a navigation command moves the highlight, as a `move-cursor` signal.
+fn movement(command: KeyAction) -> Option<(gtk::MovementStep, i32)> {
+    use gtk::MovementStep::{BufferEnds, DisplayLines, Pages, VisualPositions};
+    match command {
+        KeyAction::Up => Some((DisplayLines, -1)),
+        KeyAction::Down => Some((DisplayLines, 1)),
+        KeyAction::Left => Some((VisualPositions, -1)),
+        KeyAction::Right => Some((VisualPositions, 1)),
+        KeyAction::PageUp => Some((Pages, -1)),
+        KeyAction::PageDown => Some((Pages, 1)),
+        KeyAction::First => Some((BufferEnds, -1)),
+        KeyAction::Last => Some((BufferEnds, 1)),
+        _ => None,
+    }
+}
+
+/// Runs a query editing command at the text cursor, with the signals of the
+/// entry's own BackSpace and Ctrl+BackSpace, so whole graphemes and words go.
+fn edit_query(entry: &gtk::SearchEntry, command: KeyAction) {
+    let Some(text) = entry_text(entry) else {
+        return;
+    };
+    match command {
+        KeyAction::ClearQuery => entry.set_text(""),
+        KeyAction::DeleteChar => text.emit_by_name::<()>("backspace", &[]),
+        KeyAction::DeleteWord => {
+            text.emit_by_name::<()>("delete-from-cursor", &[&gtk::DeleteType::WordEnds, &-1i32])
+        }
+        _ => {}
+    }
+}
+
+/// The text widget inside the search entry, which has the focus and the
+/// input method.
+fn entry_text(entry: &gtk::SearchEntry) -> Option<gtk::Text> {
+    entry.delegate()?.downcast().ok()
+}
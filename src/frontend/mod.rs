@@ -1,12 +1,30 @@
 use gtk::{Application, Image, Window};
 use gtk::{Label, prelude::*};
 
-use crate::data::{Icon, InputData, Layout};
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-pub mod grid;
-pub mod list;
+use crate::data::{Action, Icon, InputData, Item, SearchBar};
+use crate::picker::Selection;
 
-pub fn run_ui(app: &Application, data: InputData, tx: std::sync::mpsc::Sender<i32>) {
+mod controller;
+mod grid;
+mod list;
+mod model;
+
+/// Set once by whatever ends the picker and read after the main loop returns.
+pub type Outcome = Rc<RefCell<Option<Selection>>>;
+
+/// Runs the action of an item that keeps the picker open. Returns the items
+/// to show instead of the current ones, after a reload.
+pub type KeepOpen = Rc<dyn Fn(&Selection) -> Option<Vec<Item>>>;
+
+pub fn run_ui(app: &Application, data: InputData, outcome: Outcome, keep_open: Option<KeepOpen>) {
     let window = Window::builder()
         .application(app)
         .title("pppicker")
@@ -17,21 +35,103 @@ pub fn run_ui(app: &Application, data: InputData, tx: std::sync::mpsc::Sender<i3
 
     window.add_css_class("picker-window");
 
-    let content = match data.view.layout {
-        Layout::List => list::create_picker(&data, tx.clone(), window.clone()),
-        Layout::Grid => grid::create_picker(&data, tx.clone(), window.clone()),
-    };
+    // Closing the window without a choice, e.g. from the compositor, cancels.
+    let outcome_close = Rc::clone(&outcome);
+    window.connect_close_request(move |_| {
+        outcome_close
+            .borrow_mut()
+            .get_or_insert(Selection::Cancelled);
+        glib::Propagation::Proceed
+    });
 
-    window.set_child(Some(&content));
+    let session = Session {
+        window: window.clone(),
+        outcome,
+        keep_open,
+        data: Rc::new(data),
+    };
+    session.show(None);
     window.present();
 }
 
-pub struct FilterState {
-    labels: Vec<String>,
+/// The window and data of the shown picker, for whatever ends it.
+#[derive(Clone)]
+struct Session {
+    window: Window,
+    outcome: Outcome,
+    keep_open: Option<KeepOpen>,
+    data: Rc<InputData>,
+}
+
+/// Query and selected item id to bring back after a reload.
+struct Restore {
     query: String,
+    selected: String,
+}
+
+impl Session {
+    fn show(&self, restore: Option<&Restore>) {
+        let content = controller::create_picker(self, restore);
+        self.window.set_child(Some(&content));
+        // The search entry if there is one, else the list or grid.
+        content.child_focus(gtk::DirectionType::TabForward);
+    }
+
+    /// Ends the picker with `selection`, or runs the action of a chosen
+    /// keep-open item and stays.
+    fn finish(&self, selection: Selection) {
+        if let Some(keep_open) = &self.keep_open
+            && let Selection::Chosen { item, query, .. } = &selection
+            && self.keeps_open(item)
+        {
+            if let Some(items) = keep_open(&selection) {
+                let restore = Restore {
+                    query: query.clone(),
+                    selected: item.id.clone(),
+                };
+                let session = Session {
+                    data: Rc::new(InputData {
+                        items,
+                        ..(*self.data).clone()
+                    }),
+                    ..self.clone()
+                };
+                // Not from inside a handler of the widgets being replaced.
+                glib::idle_add_local_once(move || session.show(Some(&restore)));
+            }
+            return;
+        }
+
+        // The first outcome wins, so a close-request after it cannot turn it into a cancel.
+        self.outcome.borrow_mut().get_or_insert(selection);
+        self.window.close();
+    }
+
+    fn keeps_open(&self, item: &Item) -> bool {
+        let action = item.action.as_ref().or(self.data.default_action.as_ref());
+        item.keep_open || matches!(action, Some(Action::Reload { .. }))
+    }
+}
+
+const ITEM_INDEX_KEY: &str = "picker-item-index";
+
+// Cells are reused for other items while scrolling, and their position
+// changes with the search ranking.
+fn set_item_index(widget: &impl IsA<gtk::Widget>, index: usize) {
+    unsafe { widget.set_data(ITEM_INDEX_KEY, index) };
 }
 
-fn create_icon(icon: &Option<Icon>) -> Option<gtk::Widget> {
+fn item_index(widget: &impl IsA<gtk::Widget>) -> Option<usize> {
+    unsafe {
+        widget
+            .data::<usize>(ITEM_INDEX_KEY)
+            .map(|index| *index.as_ref())
+    }
+}
+
+/// Creates the icon widget. Images from a path are shown `size` pixels wide
+/// and high, and come from `images` once decoded.
+fn create_icon(icon: &Option<Icon>, images: &Rc<ImageCache>, size: i32) -> Option<gtk::Widget> {
     icon.as_ref().map(|icon| match icon {
         Icon::Unicode(text) => {
             let label = Label::new(Some(text));
@@ -40,14 +140,158 @@ fn create_icon(icon: &Option<Icon>) -> Option<gtk::Widget> {
             label.upcast()
         }
         Icon::Path(path) => {
-            let image = Image::from_file(path);
+            let image = Image::new();
             image.add_css_class("picker-icon");
             image.add_css_class("picker-icon-path");
+            match images.get(path) {
+                Some(texture) => image.set_paintable(Some(&texture)),
+                None => load_image(&image, path, size, images),
+            }
             image.upcast()
         }
     })
 }
 
+// Decoding large images takes a while, so it happens off the main thread, and
+// only down to the size they are shown at. Cells showing the same image share
+// one decode. The result goes into the cache even if the image left its cell
+// in the meantime, for when it scrolls back.
+fn load_image(image: &Image, path: &Path, size: i32, images: &Rc<ImageCache>) {
+    let cancelled = match images.loading.borrow_mut().entry(path.to_path_buf()) {
+        Entry::Occupied(mut entry) => {
+            let loading = entry.get_mut();
+            loading.waiting.push(image.downgrade());
+            loading.cancelled.store(false, Ordering::Relaxed);
+            return;
+        }
+        Entry::Vacant(entry) => entry
+            .insert(Loading {
+                cancelled: Arc::default(),
+                waiting: vec![image.downgrade()],
+            })
+            .cancelled
+            .clone(),
+    };
+
+    let path = path.to_path_buf();
+    let images = Rc::downgrade(images);
+    glib::spawn_future_local(async move {
+        let file = path.clone();
+        let texture = gio::spawn_blocking(move || {
+            (!cancelled.load(Ordering::Relaxed)).then(|| {
+                gtk::gdk_pixbuf::Pixbuf::from_file_at_scale(file, size, size, true)
+                    .map(|pixbuf| gtk::gdk::Texture::for_pixbuf(&pixbuf))
+            })
+        })
+        .await;
+        let Some(images) = images.upgrade() else {
+            return;
+        };
+        let waiting: Vec<Image> = images
+            .loading
+            .borrow_mut()
+            .remove(&path)
+            .into_iter()
+            .flat_map(|loading| loading.waiting)
+            .filter_map(|image| image.upgrade())
+            .collect();
+
+        match texture {
+            Ok(Some(Ok(texture))) => {
+                images.insert(path, texture.clone());
+                for image in waiting {
+                    image.set_paintable(Some(&texture));
+                }
+            }
+            // Skipped, but a cell asked for the image again before it was.
+            Ok(None) => {
+                for image in waiting {
+                    load_image(&image, &path, size, &images);
+                }
+            }
+            Ok(Some(Err(err))) => {
+                tracing::warn!("Failed to load image {}: {}", path.display(), err);
+                for image in waiting {
+                    image.set_icon_name(Some("image-missing"));
+                }
+            }
+            Err(_) => tracing::warn!("Loading image {} panicked", path.display()),
+        }
+    });
+}
+
+/// Images decoded for the cells, by path. Holds the most recently used ones,
+/// and the images waiting for each decode that has not finished.
+#[derive(Default)]
+struct ImageCache {
+    textures: RefCell<VecDeque<(PathBuf, gtk::gdk::Texture)>>,
+    loading: RefCell<HashMap<PathBuf, Loading>>,
+}
+
+struct Loading {
+    /// Set once no image waits anymore, so that the decode is skipped if it
+    /// has not started yet.
+    cancelled: Arc<AtomicBool>,
+    waiting: Vec<glib::WeakRef<Image>>,
+}
+
+const IMAGE_CACHE_SIZE: usize = 256;
+
+impl ImageCache {
+    fn get(&self, path: &Path) -> Option<gtk::gdk::Texture> {
+        let mut textures = self.textures.borrow_mut();
+        let position = textures.iter().position(|(other, _)| other == path)?;
+        let entry = textures.remove(position)?;
+        let texture = entry.1.clone();
+        textures.push_back(entry);
+        Some(texture)
+    }
+
+    fn insert(&self, path: PathBuf, texture: gtk::gdk::Texture) {
+        let mut textures = self.textures.borrow_mut();
+        textures.retain(|(other, _)| *other != path);
+        if textures.len() >= IMAGE_CACHE_SIZE {
+            textures.pop_front();
+        }
+        textures.push_back((path, texture));
+    }
+
+    /// Stops loading into `image`, which left its cell.
+    fn release(&self, image: &Image) {
+        for loading in self.loading.borrow_mut().values_mut() {
+            loading
+                .waiting
+                .retain(|other| other.upgrade().is_some_and(|other| other != *image));
+            if loading.waiting.is_empty() {
+                loading.cancelled.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+fn create_scrolled(view: &impl IsA<gtk::Widget>) -> gtk::ScrolledWindow {
+    let scrolled = gtk::ScrolledWindow::builder()
+        .hscrollbar_policy(gtk::PolicyType::Never)
+        .vscrollbar_policy(gtk::PolicyType::Automatic)
+        .vexpand(true)
+        .child(view)
+        .build();
+    scrolled.add_css_class("picker-scrolled");
+    scrolled
+}
+
+fn create_description(text: &str) -> Label {
+    let label = Label::builder()
+        .label(text)
+        .xalign(0.0)
+        .ellipsize(gtk::pango::EllipsizeMode::End)
+        .build();
+    label.add_css_class("picker-description");
+    label
+}
+
+const DEFAULT_HIGHLIGHT: &str = r#"weight="bold" underline="single""#;
+
 fn create_label(text: &str) -> Label {
     let label = Label::builder()
         .label(text)
@@ -57,3 +301,58 @@ fn create_label(text: &str) -> Label {
     label.add_css_class("picker-label");
     label
 }
+
+fn highlight_attributes(config: &SearchBar) -> String {
+    let Some(attributes) = &config.highlight else {
+        return DEFAULT_HIGHLIGHT.to_string();
+    };
+
+    let probe = format!("<span {}>x</span>", attributes);
+    match gtk::pango::parse_markup(&probe, '\0') {
+        Ok(_) => attributes.clone(),
+        Err(err) => {
+            tracing::warn!("Invalid search-bar highlight '{}': {}", attributes, err);
+            DEFAULT_HIGHLIGHT.to_string()
+        }
+    }
+}
+
+fn set_label_matches(label: &Label, text: &str, positions: &[usize], highlight: &str) {
+    if positions.is_empty() {
+        label.set_text(text);
+        label.remove_css_class("picker-match");
+        return;
+    }
+
+    label.set_markup(&match_markup(text, positions, highlight));
+    label.add_css_class("picker-match");
+}
+
+fn match_markup(text: &str, positions: &[usize], highlight: &str) -> String {
+    let mut markup = String::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+    let mut positions = positions.iter().peekable();
+
+    for (index, c) in text.chars().enumerate() {
+        let matched = positions.next_if_eq(&&index).is_some();
+        if matched != run_matched && !run.is_empty() {
+            push_run(&mut markup, &run, run_matched, highlight);
+            run.clear();
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    push_run(&mut markup, &run, run_matched, highlight);
+
+    markup
+}
+
+fn push_run(markup: &mut String, run: &str, matched: bool, highlight: &str) {
+    let escaped = glib::markup_escape_text(run);
+    if matched {
+        markup.push_str(&format!("<span {}>{}</span>", highlight, escaped));
+    } else {
+        markup.push_str(&escaped);
+    }
+}
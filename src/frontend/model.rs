@@ -0,0 +1,369 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
+
+use gtk::Label;
+use gtk::prelude::*;
+
+use crate::data::InputData;
+use crate::frontend::{ImageCache, create_icon, item_index, set_item_index, set_label_matches};
+use crate::matcher::{FilterState, QueryChange};
+
+/// Widgets of a row or grid cell, filled in for whichever item it shows.
+pub(super) struct Cell {
+    pub(super) root: gtk::Box,
+    pub(super) label: Label,
+    pub(super) description: Option<Label>,
+}
+
+/// The part of a cell that changes with its item. Kept on the root widget.
+struct Parts {
+    label: Label,
+    description: Option<Label>,
+    icon: RefCell<Option<gtk::Widget>>,
+}
+
+const PARTS_KEY: &str = "picker-cell-parts";
+
+/// The items as a filtered and sorted list model for `ListView` and
+/// `GridView`. Those only build cells for the items on screen, and reuse
+/// them for other items while scrolling.
+pub(super) struct ItemModel {
+    selection: gtk::SingleSelection,
+    filter: gtk::CustomFilter,
+    sorter: gtk::CustomSorter,
+    shared: Rc<Shared>,
+}
+
+struct Shared {
+    data: Rc<InputData>,
+    /// CSS class of the view's own row or child widget around a cell.
+    item_class: &'static str,
+    /// Size of image icons in the default style.
+    icon_size: i32,
+    images: Rc<ImageCache>,
+    search: RefCell<Option<Search>>,
+    marked: RefCell<HashSet<usize>>,
+    /// Cell root of each item that is shown.
+    bound: RefCell<HashMap<usize, gtk::Box>>,
+    activated: RefCell<Option<OnActivated>>,
+}
+
+/// Called with the index of a clicked item.
+type OnActivated = Box<dyn Fn(usize)>;
+
+struct Search {
+    state: Rc<RefCell<FilterState>>,
+    highlight: String,
+}
+
+impl ItemModel {
+    pub(super) fn new(data: Rc<InputData>, item_class: &'static str, icon_size: i32) -> Self {
+        let store = gio::ListStore::new::<glib::BoxedAnyObject>();
+        let objects: Vec<glib::BoxedAnyObject> = (0..data.items.len())
+            .map(glib::BoxedAnyObject::new)
+            .collect();
+        store.extend_from_slice(&objects);
+
+        let shared = Rc::new(Shared {
+            data,
+            item_class,
+            icon_size,
+            images: Rc::default(),
+            search: RefCell::default(),
+            marked: RefCell::default(),
+            bound: RefCell::default(),
+            activated: RefCell::default(),
+        });
+
+        let shared_filter = Rc::clone(&shared);
+        let filter = gtk::CustomFilter::new(move |object| {
+            shared_filter
+                .search
+                .borrow()
+                .as_ref()
+                .is_none_or(|search| search.state.borrow().is_visible(object_index(object)))
+        });
+        let shared_sorter = Rc::clone(&shared);
+        let sorter = gtk::CustomSorter::new(move |a, b| {
+            let (a, b) = (object_index(a), object_index(b));
+            match shared_sorter.search.borrow().as_ref() {
+                Some(search) => search.state.borrow().compare(a, b).into(),
+                None => a.cmp(&b).into(),
+            }
+        });
+
+        let filtered = gtk::FilterListModel::new(Some(store), Some(filter.clone()));
+        let sorted = gtk::SortListModel::new(Some(filtered), Some(sorter.clone()));
+        let selection = gtk::SingleSelection::new(Some(sorted));
+        selection.set_autoselect(false);
+        selection.set_can_unselect(true);
+        if selection.n_items() > 0 {
+            selection.set_selected(0);
+        }
+
+        Self {
+            selection,
+            filter,
+            sorter,
+            shared,
+        }
+    }
+
+    pub(super) fn selection(&self) -> &gtk::SingleSelection {
+        &self.selection
+    }
+
+    /// Factory for the view, with `create_cell` building empty cells.
+    pub(super) fn factory(
+        &self,
+        create_cell: impl Fn() -> Cell + 'static,
+    ) -> gtk::SignalListItemFactory {
+        let factory = gtk::SignalListItemFactory::new();
+
+        let shared = Rc::downgrade(&self.shared);
+        factory.connect_setup(move |_, list_item| {
+            let cell = create_cell();
+            let root = cell.root;
+            let parts = Parts {
+                label: cell.label,
+                description: cell.description,
+                icon: RefCell::default(),
+            };
+            unsafe { root.set_data(PARTS_KEY, parts) };
+
+            // Clicking an item chooses it, like Enter.
+            let click = gtk::GestureClick::new();
+            let shared = Weak::clone(&shared);
+            click.connect_released(move |gesture, _, _, _| {
+                if let Some(shared) = shared.upgrade()
+                    && let Some(index) = gesture.widget().and_then(|root| item_index(&root))
+                    && let Some(activated) = &*shared.activated.borrow()
+                {
+                    activated(index);
+                }
+            });
+            root.add_controller(click);
+
+            list_item.set_child(Some(&root));
+        });
+
+        let shared = Rc::clone(&self.shared);
+        factory.connect_bind(move |_, list_item| {
+            if let Some((index, root)) = bound_cell(list_item) {
+                shared.bind(index, &root);
+            }
+        });
+
+        let shared = Rc::clone(&self.shared);
+        factory.connect_unbind(move |_, list_item| {
+            if let Some((index, root)) = bound_cell(list_item) {
+                shared.unbind(index, &root);
+            }
+        });
+
+        factory
+    }
+
+    pub(super) fn selected(&self) -> Option<usize> {
+        self.selection
+            .selected_item()
+            .map(|object| object_index(&object))
+    }
+
+    /// Selects the item, or nothing with `None`. Returns its position in the view.
+    pub(super) fn select(&self, index: Option<usize>) -> Option<u32> {
+        let position = index.and_then(|index| {
+            (0..self.selection.n_items()).find(|&position| {
+                self.selection
+                    .item(position)
+                    .is_some_and(|object| object_index(&object) == index)
+            })
+        });
+        self.selection
+            .set_selected(position.unwrap_or(gtk::INVALID_LIST_POSITION));
+        position
+    }
+
+    /// Moves the selection like a `move-cursor` signal, with `columns` items
+    /// per line and `page` items per page. Returns the new position.
+    pub(super) fn move_cursor(
+        &self,
+        step: gtk::MovementStep,
+        count: i32,
+        columns: u32,
+        page: u32,
+    ) -> Option<u32> {
+        let last = self.selection.n_items().checked_sub(1)?;
+        let current = match self.selection.selected() {
+            gtk::INVALID_LIST_POSITION => 0,
+            position => position,
+        };
+        let distance = match step {
+            gtk::MovementStep::DisplayLines => columns,
+            gtk::MovementStep::Pages => page,
+            gtk::MovementStep::BufferEnds => last,
+            _ => 1,
+        };
+        let position = if count < 0 {
+            current.saturating_sub(distance)
+        } else {
+            current.saturating_add(distance).min(last)
+        };
+        self.selection.set_selected(position);
+        Some(position)
+    }
+
+    /// Size of the view's widget around a shown cell, if any is shown.
+    pub(super) fn cell_size(&self) -> Option<(i32, i32)> {
+        let bound = self.shared.bound.borrow();
+        let item = bound.values().next()?.parent()?;
+        Some((item.width(), item.height()))
+    }
+
+    pub(super) fn set_marked(&self, index: usize, marked: bool) {
+        if marked {
+            self.shared.marked.borrow_mut().insert(index);
+        } else {
+            self.shared.marked.borrow_mut().remove(&index);
+        }
+        if let Some(root) = self.shared.bound.borrow().get(&index) {
+            self.shared.show_marked(index, root);
+        }
+    }
+
+    pub(super) fn set_filter(&self, state: Rc<RefCell<FilterState>>, highlight: String) {
+        self.shared
+            .search
+            .replace(Some(Search { state, highlight }));
+        self.refilter(QueryChange::Different);
+    }
+
+    pub(super) fn refilter(&self, change: QueryChange) {
+        // The filter model then only checks the items that were shown, or
+        // the ones that were not. Scores change either way.
+        self.filter.changed(match change {
+            QueryChange::MoreStrict => gtk::FilterChange::MoreStrict,
+            QueryChange::LessStrict => gtk::FilterChange::LessStrict,
+            QueryChange::Different => gtk::FilterChange::Different,
+        });
+        self.sorter.changed(gtk::SorterChange::Different);
+
+        // Cells that stay on screen are not bound again.
+        let bound: Vec<(usize, gtk::Box)> = self
+            .shared
+            .bound
+            .borrow()
+            .iter()
+            .map(|(&index, root)| (index, root.clone()))
+            .collect();
+        for (index, root) in bound {
+            if let Some(parts) = parts(&root) {
+                self.shared.show_matches(index, parts);
+            }
+        }
+    }
+
+    pub(super) fn connect_activated(&self, callback: OnActivated) {
+        self.shared.activated.replace(Some(callback));
+    }
+}
+
+impl Shared {
+    fn bind(&self, index: usize, root: &gtk::Box) {
+        let Some(parts) = parts(root) else {
+            return;
+        };
+        let Some(item) = self.data.items.get(index) else {
+            return;
+        };
+        set_item_index(root, index);
+
+        self.remove_icon(root, parts);
+        let icon_size = self.icon_size * root.scale_factor();
+        if let Some(icon) = create_icon(&item.icon, &self.images, icon_size) {
+            root.prepend(&icon);
+            parts.icon.replace(Some(icon));
+        }
+
+        if let Some(label) = &parts.description {
+            label.set_text(item.description.as_deref().unwrap_or_default());
+            label.set_visible(item.description.is_some());
+        }
+
+        self.show_matches(index, parts);
+        if let Some(parent) = root.parent() {
+            parent.add_css_class(self.item_class);
+        }
+        self.show_marked(index, root);
+        self.bound.borrow_mut().insert(index, root.clone());
+    }
+
+    fn unbind(&self, index: usize, root: &gtk::Box) {
+        let mut bound = self.bound.borrow_mut();
+        if bound.get(&index) == Some(root) {
+            bound.remove(&index);
+        }
+        if let Some(parts) = parts(root) {
+            self.remove_icon(root, parts);
+        }
+    }
+
+    // An image that is still loading goes into the cache, unless no other cell
+    // waits for it and its decode has not started yet.
+    fn remove_icon(&self, root: &gtk::Box, parts: &Parts) {
+        if let Some(icon) = parts.icon.take() {
+            if let Some(image) = icon.downcast_ref::<gtk::Image>() {
+                self.images.release(image);
+            }
+            root.remove(&icon);
+        }
+    }
+
+    fn show_matches(&self, index: usize, parts: &Parts) {
+        let label = &self.data.items[index].label;
+        match &*self.search.borrow() {
+            Some(search) => set_label_matches(
+                &parts.label,
+                label,
+                &search.state.borrow().positions(index),
+                &search.highlight,
+            ),
+            None => parts.label.set_text(label),
+        }
+    }
+
+    fn show_marked(&self, index: usize, root: &gtk::Box) {
+        let Some(parent) = root.parent() else {
+            return;
+        };
+        if self.marked.borrow().contains(&index) {
+            parent.add_css_class("picker-marked");
+        } else {
+            parent.remove_css_class("picker-marked");
+        }
+    }
+}
+
+/// Scrolls the view to the item at `position`.
+pub(super) fn scroll_to(view: &impl IsA<gtk::Widget>, position: u32) {
+    if let Err(err) = view.activate_action("list.scroll-to-item", Some(&position.to_variant())) {
+        tracing::debug!("Failed to scroll to item {}: {}", position, err);
+    }
+}
+
+fn object_index(object: &glib::Object) -> usize {
+    object
+        .downcast_ref::<glib::BoxedAnyObject>()
+        .map_or(usize::MAX, |object| *object.borrow::<usize>())
+}
+
+fn bound_cell(list_item: &gtk::ListItem) -> Option<(usize, gtk::Box)> {
+    let index = object_index(&list_item.item()?);
+    let root = list_item.child()?.downcast::<gtk::Box>().ok()?;
+    Some((index, root))
+}
+
+fn parts(root: &gtk::Box) -> Option<&Parts> {
+    unsafe { root.data::<Parts>(PARTS_KEY).map(|parts| parts.as_ref()) }
+}
@@ -0,0 +1,335 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use glib::translate::IntoGlib;
+use gtk::gdk::{Key, ModifierType};
+
+use crate::data::{Action, InputData, Item, KeyAction};
+
+/// Modifiers that take part in a key combination. Others, like Num Lock, are ignored.
+const MODIFIERS: ModifierType = ModifierType::CONTROL_MASK
+    .union(ModifierType::ALT_MASK)
+    .union(ModifierType::SHIFT_MASK)
+    .union(ModifierType::SUPER_MASK);
+
+/// A key with modifiers, written like `ctrl+Return`, `alt+1` or `ctrl+shift+c`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyCombo {
+    key: Key,
+    modifiers: ModifierType,
+}
+
+impl KeyCombo {
+    /// Whether a key press with these modifiers is this combination.
+    pub fn matches(&self, key: Key, modifiers: ModifierType) -> bool {
+        self.key.to_lower() == key.to_lower() && self.modifiers == modifiers & MODIFIERS
+    }
+
+    /// Whether the key types a character, like `space` or `shift+a`.
+    fn is_text(&self) -> bool {
+        (self.modifiers - ModifierType::SHIFT_MASK).is_empty()
+            && self.key.to_unicode().is_some_and(|c| !c.is_control())
+    }
+}
+
+impl FromStr for KeyCombo {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (modifier_names, name) = match s.rsplit_once('+') {
+            Some((modifiers, name)) => (Some(modifiers), name),
+            None => (None, s),
+        };
+
+        let mut modifiers = ModifierType::empty();
+        for modifier in modifier_names
+            .into_iter()
+            .flat_map(|names| names.split('+'))
+        {
+            modifiers |= match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => ModifierType::CONTROL_MASK,
+                "alt" => ModifierType::ALT_MASK,
+                "shift" => ModifierType::SHIFT_MASK,
+                "super" => ModifierType::SUPER_MASK,
+                _ => anyhow::bail!(
+                    "Unknown modifier '{}' in key '{}' (expected ctrl, alt, shift or super)",
+                    modifier,
+                    s
+                ),
+            };
+        }
+
+        let key = match name.to_lowercase().as_str() {
+            "enter" => Some(Key::Return),
+            "esc" => Some(Key::Escape),
+            _ => Key::from_name(name),
+        };
+        match key {
+            Some(key) => Ok(KeyCombo {
+                key: key.to_lower(),
+                modifiers,
+            }),
+            None => anyhow::bail!("Unknown key name '{}' in key '{}'", name, s),
+        }
+    }
+}
+
+impl fmt::Display for KeyCombo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (modifier, name) in [
+            (ModifierType::CONTROL_MASK, "ctrl"),
+            (ModifierType::ALT_MASK, "alt"),
+            (ModifierType::SHIFT_MASK, "shift"),
+            (ModifierType::SUPER_MASK, "super"),
+        ] {
+            if self.modifiers.contains(modifier) {
+                write!(f, "{}+", name)?;
+            }
+        }
+        match self.key.name() {
+            Some(name) => write!(f, "{}", name),
+            None => write!(f, "{:#x}", self.key.into_glib()),
+        }
+    }
+}
+
+const DEFAULT_KEYS: &[(KeyAction, &[&str])] = &[
+    (KeyAction::Accept, &["Return", "KP_Enter"]),
+    (KeyAction::SubmitQuery, &["shift+Return", "shift+KP_Enter"]),
+    (KeyAction::Cancel, &["Escape"]),
+    (KeyAction::ClearQuery, &["ctrl+u"]),
+    (KeyAction::DeleteChar, &["BackSpace"]),
+    (KeyAction::DeleteWord, &["ctrl+w", "ctrl+BackSpace"]),
+    (
+        KeyAction::ToggleMark,
+        &["Tab", "shift+ISO_Left_Tab", "space"],
+    ),
+    (KeyAction::Up, &["Up", "ctrl+k", "ctrl+p"]),
+    (KeyAction::Down, &["Down", "ctrl+j", "ctrl+n"]),
+    (KeyAction::Left, &["Left"]),
+    (KeyAction::Right, &["Right"]),
+    (KeyAction::PageUp, &["Page_Up"]),
+    (KeyAction::PageDown, &["Page_Down"]),
+    (KeyAction::First, &["Home"]),
+    (KeyAction::Last, &["End"]),
+];
+
+/// Keys of the built-in commands: the defaults, with the picker's `keys`
+/// replacing them per command.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    keys: Vec<(KeyCombo, KeyAction)>,
+    /// Commands whose keys come from `keys` rather than the defaults.
+    configured: BTreeSet<KeyAction>,
+}
+
+impl Keymap {
+    pub fn new(overrides: &BTreeMap<KeyAction, Vec<String>>) -> Self {
+        let mut keys = Vec::new();
+        for &(action, defaults) in DEFAULT_KEYS {
+            let specs: Vec<&str> = match overrides.get(&action) {
+                Some(specs) => specs.iter().map(String::as_str).collect(),
+                None => defaults.to_vec(),
+            };
+            for spec in specs {
+                match spec.parse() {
+                    Ok(combo) => keys.push((combo, action)),
+                    Err(err) => tracing::warn!("Ignoring key for {}: {}", action, err),
+                }
+            }
+        }
+        Self {
+            keys,
+            configured: overrides.keys().copied().collect(),
+        }
+    }
+
+    /// The command of a key press. With `typing`, keys that type a character
+    /// go to the search bar instead.
+    pub fn lookup(&self, key: Key, modifiers: ModifierType, typing: bool) -> Option<KeyAction> {
+        self.keys
+            .iter()
+            .find(|(combo, _)| combo.matches(key, modifiers) && !(typing && combo.is_text()))
+            .map(|&(_, action)| action)
+    }
+
+    pub fn is_configured(&self, action: KeyAction) -> bool {
+        self.configured.contains(&action)
+    }
+}
+
+/// Every key spec in `keys` that does not parse, with its command.
+pub fn check_keys(keys: &BTreeMap<KeyAction, Vec<String>>) -> Vec<(KeyAction, anyhow::Error)> {
+    keys.iter()
+        .flat_map(|(&action, specs)| {
+            specs
+                .iter()
+                .filter_map(move |spec| spec.parse::<KeyCombo>().err().map(|err| (action, err)))
+        })
+        .collect()
+}
+
+/// Action bound to `key` in a `bindings` table, however the key is spelled there.
+pub fn bound_action<'a>(
+    bindings: &'a BTreeMap<String, Action>,
+    key: &KeyCombo,
+) -> Option<&'a Action> {
+    bindings
+        .iter()
+        .find(|(spec, _)| spec.parse::<KeyCombo>().is_ok_and(|combo| combo == *key))
+        .map(|(_, action)| action)
+}
+
+/// What a key press does besides the built-in keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// Run the highlighted item's action for this key.
+    Action(KeyCombo),
+    /// The `custom-keys` entry at this position.
+    Custom(usize),
+}
+
+/// Keys from the picker's `custom-keys` and every `bindings` table.
+#[derive(Debug, Clone, Default)]
+pub struct Bindings {
+    actions: Vec<KeyCombo>,
+    /// Keys in the picker's own `bindings`, which apply to every item.
+    defaults: Vec<KeyCombo>,
+    // Invalid entries stay as `None` so the others keep their exit codes.
+    custom: Vec<Option<KeyCombo>>,
+}
+
+impl Bindings {
+    pub fn new(data: &InputData) -> Self {
+        let parse = |spec: &String| {
+            spec.parse()
+                .inspect_err(|err| tracing::warn!("Ignoring binding: {}", err))
+                .ok()
+        };
+        let defaults: Vec<KeyCombo> = data.bindings.keys().filter_map(parse).collect();
+        let mut actions = defaults.clone();
+        for combo in data
+            .items
+            .iter()
+            .flat_map(|item| item.bindings.keys().filter_map(parse))
+        {
+            if !actions.contains(&combo) {
+                actions.push(combo);
+            }
+        }
+
+        let custom = data
+            .custom_keys
+            .iter()
+            .map(|spec| {
+                spec.parse()
+                    .inspect_err(|err| tracing::warn!("Ignoring custom key: {}", err))
+                    .ok()
+            })
+            .collect();
+
+        Self {
+            actions,
+            defaults,
+            custom,
+        }
+    }
+
+    /// Whether `key` has an action for `item`, from the item or the picker.
+    pub fn binds(&self, item: &Item, key: &KeyCombo) -> bool {
+        self.defaults.contains(key) || bound_action(&item.bindings, key).is_some()
+    }
+
+    pub fn lookup(&self, key: Key, modifiers: ModifierType) -> Option<Binding> {
+        if let Some(number) = self
+            .custom
+            .iter()
+            .position(|combo| combo.is_some_and(|combo| combo.matches(key, modifiers)))
+        {
+            return Some(Binding::Custom(number));
+        }
+        self.actions
+            .iter()
+            .find(|combo| combo.matches(key, modifiers))
+            .map(|combo| Binding::Action(*combo))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn combo(s: &str) -> KeyCombo {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parses_modifiers_and_key() {
+        let parsed = combo("ctrl+shift+c");
+        assert_eq!(parsed.key, Key::c);
+        assert_eq!(
+            parsed.modifiers,
+            ModifierType::CONTROL_MASK | ModifierType::SHIFT_MASK
+        );
+        assert_eq!(combo("Return").modifiers, ModifierType::empty());
+    }
+
+    #[test]
+    fn modifiers_are_case_insensitive() {
+        assert_eq!(combo("CTRL+Return"), combo("ctrl+Return"));
+        assert_eq!(combo("Control+Return"), combo("ctrl+Return"));
+        assert_eq!(combo("Alt+Super+1"), combo("super+alt+1"));
+    }
+
+    #[test]
+    fn aliases() {
+        assert_eq!(combo("enter"), combo("Return"));
+        assert_eq!(combo("Enter"), combo("Return"));
+        assert_eq!(combo("esc"), combo("Escape"));
+        assert_eq!(combo("ctrl+ESC"), combo("ctrl+Escape"));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for s in ["ctrl+Return", "alt+1", "ctrl+shift+c", "super+space", "F5"] {
+            assert_eq!(combo(s).to_string(), s);
+            assert_eq!(combo(&combo(s).to_string()), combo(s));
+        }
+        // Modifiers are written in a fixed order.
+        assert_eq!(combo("shift+ctrl+c").to_string(), "ctrl+shift+c");
+    }
+
+    #[test]
+    fn rejects_unknown_modifiers() {
+        let err = "hyper+Return".parse::<KeyCombo>().unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Unknown modifier 'hyper' in key 'hyper+Return' (expected ctrl, alt, shift or super)"
+        );
+        assert!("ctrl++a".parse::<KeyCombo>().is_err());
+    }
+
+    #[test]
+    fn matches_ignores_other_modifiers_and_case() {
+        let ctrl_c = combo("ctrl+c");
+        assert!(ctrl_c.matches(Key::c, ModifierType::CONTROL_MASK));
+        assert!(ctrl_c.matches(Key::C, ModifierType::CONTROL_MASK));
+        // Num Lock is reported as Mod2.
+        assert!(ctrl_c.matches(
+            Key::c,
+            ModifierType::CONTROL_MASK | ModifierType::from_bits_retain(1 << 4)
+        ));
+        assert!(!ctrl_c.matches(Key::c, ModifierType::empty()));
+        assert!(!ctrl_c.matches(Key::c, ModifierType::CONTROL_MASK | ModifierType::ALT_MASK));
+        assert!(!ctrl_c.matches(Key::v, ModifierType::CONTROL_MASK));
+    }
+
+    #[test]
+    fn text_keys() {
+        assert!(combo("space").is_text());
+        assert!(combo("shift+a").is_text());
+        assert!(!combo("ctrl+a").is_text());
+        assert!(!combo("Return").is_text());
+    }
+}
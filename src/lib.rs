@@ -1,9 +1,30 @@
 mod app;
 mod cli;
+mod config;
 mod core;
 mod data;
 mod frontend;
+mod keys;
+mod matcher;
+mod picker;
+mod schema;
+mod template;
+mod validate;
 
+pub use crate::core::{
+    EXIT_CANCELLED, EXIT_CUSTOM_KEY, EXIT_ERROR, EXIT_NO_MATCH, EXIT_SELECTED, EXIT_TIMEOUT,
+    run_action, run_actions,
+};
 pub use app::run;
-pub use cli::read_input;
-pub use data::InputData;
+pub use cli::{Command, Input, Options, Source, parse_args};
+pub use config::Config;
+pub use data::{
+    Action, CustomInput, Exec, ExecMode, ExitCodes, Format, Icon, InputData, Item, KeyAction,
+    Layout, MarkedOutput, MultiSelect, Output, OutputFormat, SearchBar, SearchWeights, Separator,
+    View,
+};
+pub use keys::KeyCombo;
+pub use picker::{Picker, Selection};
+pub use schema::schema;
+pub use template::{BUILTIN_PLACEHOLDERS, Placeholders};
+pub use validate::{Diagnostic, Location, Report, Severity, validate};
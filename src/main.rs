@@ -1,9 +1,23 @@
-use pppicker::run;
+use pppicker::{EXIT_ERROR, run};
 use tracing::info;
 use tracing_appender::rolling;
 use tracing_subscriber::{fmt, layer::SubscriberExt, util::SubscriberInitExt};
 
-fn main() -> anyhow::Result<()> {
+fn main() {
+    if let Err(err) = init_logging() {
+        eprintln!("Error: {:?}", err);
+        std::process::exit(EXIT_ERROR);
+    }
+
+    info!("Starting pppicker");
+    let code = run().unwrap_or_else(|err| {
+        eprintln!("Error: {:?}", err);
+        EXIT_ERROR
+    });
+    std::process::exit(code);
+}
+
+fn init_logging() -> anyhow::Result<()> {
     let log_dir = dirs_next::cache_dir()
         .unwrap_or_else(std::env::temp_dir)
         .join("pppicker");
@@ -24,7 +38,5 @@ fn main() -> anyhow::Result<()> {
         )
         .init();
 
-    info!("Starting pppicker");
-    run()?;
     Ok(())
 }
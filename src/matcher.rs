@@ -0,0 +1,505 @@
+use std::cell::RefCell;
+use std::cmp::Ordering;
+
+use crate::data::{Item, SearchWeights};
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_PREFIX: i64 = 8;
+const BONUS_WORD_START: i64 = 12;
+const BONUS_CONSECUTIVE: i64 = 10;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP: i64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    /// Char indices into the matched text.
+    pub positions: Vec<usize>,
+}
+
+/// Matches one pattern against many texts, reusing its buffers between them.
+#[derive(Debug, Default)]
+pub struct Matcher {
+    pattern: Vec<char>,
+    original: Vec<char>,
+    folded: Vec<char>,
+    bonus: Vec<i64>,
+    scores: Vec<Option<i64>>,
+    back: Vec<usize>,
+}
+
+impl Matcher {
+    pub fn set_pattern(&mut self, pattern: &str) {
+        self.pattern.clear();
+        self.pattern.extend(pattern.chars().map(fold_case));
+    }
+
+    /// Case-insensitive subsequence match of the pattern against `text`.
+    ///
+    /// Matches on word starts, consecutive runs and a match at the very start
+    /// of `text` score higher; gaps between matched characters cost a little.
+    pub fn fuzzy_match(&mut self, text: &str) -> Option<Match> {
+        let (score, mut j) = self.run(text, true)?;
+        let (m, n) = (self.pattern.len(), self.folded.len());
+        let mut positions = vec![0; m];
+        for i in (0..m).rev() {
+            positions[i] = j;
+            j = self.back[i * n + j];
+        }
+        Some(Match { score, positions })
+    }
+
+    /// The score of [`Matcher::fuzzy_match`], without working out the positions.
+    pub fn score(&mut self, text: &str) -> Option<i64> {
+        self.run(text, false).map(|(score, _)| score)
+    }
+
+    /// Best score and the text index of the last matched character. With
+    /// `track`, `back` holds the way there.
+    fn run(&mut self, text: &str, track: bool) -> Option<(i64, usize)> {
+        let m = self.pattern.len();
+        if m == 0 {
+            self.folded.clear();
+            return Some((0, 0));
+        }
+        if !is_subsequence(&self.pattern, text) {
+            return None;
+        }
+
+        self.original.clear();
+        self.original.extend(text.chars());
+        self.folded.clear();
+        self.folded
+            .extend(self.original.iter().copied().map(fold_case));
+        let n = self.folded.len();
+        self.bonus.clear();
+        self.bonus
+            .extend((0..n).map(|j| char_bonus(&self.original, j)));
+
+        // scores[row(i) + j]: best score for pattern[..=i] with pattern[i]
+        // matched at text[j]. Without `track`, only the last two rows are kept.
+        let (pattern, folded, bonus) = (&self.pattern, &self.folded, &self.bonus);
+        let rows = if track { m } else { 2 };
+        let row = |i: usize| i % rows * n;
+        let scores = &mut self.scores;
+        scores.clear();
+        scores.resize(rows * n, None);
+        let back = &mut self.back;
+        if track {
+            back.clear();
+            back.resize(m * n, 0);
+        }
+
+        for i in 0..m {
+            let (current, previous) = (row(i), row(i + rows - 1));
+            scores[current..current + n].fill(None);
+            // Best predecessor at least one character before j - 1, with the gap already paid.
+            let mut best_gap: Option<(i64, usize)> = None;
+
+            for j in i..n {
+                if i > 0 && j >= 2 {
+                    best_gap = best_gap.map(|(score, k)| (score - PENALTY_GAP, k));
+                    if let Some(prev) = scores[previous + j - 2] {
+                        let candidate = prev - PENALTY_GAP_START;
+                        if best_gap.is_none_or(|(score, _)| candidate > score) {
+                            best_gap = Some((candidate, j - 2));
+                        }
+                    }
+                }
+
+                if pattern[i] != folded[j] {
+                    continue;
+                }
+
+                let base = SCORE_MATCH + bonus[j];
+
+                if i == 0 {
+                    let prefix = if j == 0 { BONUS_PREFIX } else { 0 };
+                    scores[current + j] = Some(base + prefix);
+                    continue;
+                }
+
+                let consecutive = if j >= 1 {
+                    scores[previous + j - 1].map(|prev| (prev + BONUS_CONSECUTIVE, j - 1))
+                } else {
+                    None
+                };
+                let gapped = best_gap;
+
+                let best = match (consecutive, gapped) {
+                    (Some(c), Some(g)) => Some(if c.0 >= g.0 { c } else { g }),
+                    (c, g) => c.or(g),
+                };
+
+                if let Some((prev, k)) = best {
+                    scores[current + j] = Some(prev + base);
+                    if track {
+                        back[i * n + j] = k;
+                    }
+                }
+            }
+        }
+
+        let last = row(m - 1);
+        (0..n)
+            .filter_map(|j| scores[last + j].map(|score| (score, j)))
+            .max_by(|a, b| a.0.cmp(&b.0).then(b.1.cmp(&a.1)))
+    }
+}
+
+fn fold_case(c: char) -> char {
+    if c.is_ascii() {
+        return c.to_ascii_lowercase();
+    }
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+fn is_subsequence(pattern: &[char], text: &str) -> bool {
+    let mut remaining = text.chars().map(fold_case);
+    pattern.iter().all(|p| remaining.any(|t| t == *p))
+}
+
+fn char_bonus(text: &[char], j: usize) -> i64 {
+    let current = text[j];
+    let Some(&prev) = j.checked_sub(1).and_then(|k| text.get(k)) else {
+        return BONUS_WORD_START;
+    };
+
+    let word_start = !prev.is_alphanumeric() && current.is_alphanumeric();
+    let camel_case = prev.is_lowercase() && current.is_uppercase();
+    let digits = !prev.is_numeric() && current.is_numeric();
+
+    if word_start || camel_case || digits {
+        BONUS_WORD_START
+    } else {
+        0
+    }
+}
+
+/// Query state shared by the layouts: which items are visible and in which order.
+pub struct FilterState {
+    candidates: Vec<Candidate>,
+    weights: SearchWeights,
+    query: String,
+    /// Weighted score of each item, `None` for items the query hides.
+    scores: Vec<Option<i64>>,
+    matcher: RefCell<Matcher>,
+}
+
+struct Candidate {
+    label: String,
+    keywords: Vec<String>,
+    description: Option<String>,
+    id: String,
+}
+
+/// How a new query changed which items are visible.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryChange {
+    /// Only items that were visible still can be, e.g. after typing a character.
+    MoreStrict,
+    /// Items that were visible still are, e.g. after deleting a character.
+    LessStrict,
+    Different,
+}
+
+impl FilterState {
+    pub fn new(items: &[Item], weights: SearchWeights) -> Self {
+        let candidates = items
+            .iter()
+            .map(|item| Candidate {
+                label: item.label.clone(),
+                keywords: item.keywords.clone(),
+                description: item.description.clone(),
+                id: item.id.clone(),
+            })
+            .collect();
+
+        Self {
+            candidates,
+            weights,
+            query: String::new(),
+            scores: vec![Some(0); items.len()],
+            matcher: RefCell::default(),
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn set_query(&mut self, query: &str) -> QueryChange {
+        let change = if query.starts_with(&self.query) {
+            QueryChange::MoreStrict
+        } else if self.query.starts_with(query) {
+            QueryChange::LessStrict
+        } else {
+            QueryChange::Different
+        };
+        // Whatever a longer query matches, the shorter one matched as well.
+        let narrowing = change == QueryChange::MoreStrict && !self.query.is_empty();
+        self.query = query.to_string();
+
+        let matcher = self.matcher.get_mut();
+        matcher.set_pattern(query);
+        for (candidate, score) in self.candidates.iter().zip(&mut self.scores) {
+            if narrowing && score.is_none() {
+                continue;
+            }
+            *score = candidate_score(matcher, candidate, &self.weights);
+        }
+        change
+    }
+
+    pub fn is_visible(&self, index: usize) -> bool {
+        self.query.is_empty() || self.scores.get(index).is_some_and(Option::is_some)
+    }
+
+    /// Char indices of the label characters matched by the current query.
+    /// Only worked out on demand, for the labels on screen.
+    pub fn positions(&self, index: usize) -> Vec<usize> {
+        let Some(candidate) = self.candidates.get(index) else {
+            return Vec::new();
+        };
+        if self.query.is_empty() || self.weights.label <= 0.0 || !self.is_visible(index) {
+            return Vec::new();
+        }
+        self.matcher
+            .borrow_mut()
+            .fuzzy_match(&candidate.label)
+            .map(|m| m.positions)
+            .unwrap_or_default()
+    }
+
+    /// Orders items by descending score, falling back to input order.
+    pub fn compare(&self, a: usize, b: usize) -> Ordering {
+        self.score(b).cmp(&self.score(a)).then(a.cmp(&b))
+    }
+
+    /// The visible item that sorts first, if any.
+    pub fn best(&self) -> Option<usize> {
+        (0..self.scores.len())
+            .filter(|&index| self.is_visible(index))
+            .min_by(|&a, &b| self.compare(a, b))
+    }
+
+    fn score(&self, index: usize) -> i64 {
+        self.scores
+            .get(index)
+            .copied()
+            .flatten()
+            .unwrap_or(i64::MIN)
+    }
+}
+
+/// Best weighted score over all searchable fields. A weight of zero or less
+/// excludes the field from the search.
+fn candidate_score(
+    matcher: &mut Matcher,
+    candidate: &Candidate,
+    weights: &SearchWeights,
+) -> Option<i64> {
+    std::iter::once((candidate.label.as_str(), weights.label))
+        .chain(
+            candidate
+                .keywords
+                .iter()
+                .map(|keyword| (keyword.as_str(), weights.keywords)),
+        )
+        .chain(
+            candidate
+                .description
+                .as_deref()
+                .map(|description| (description, weights.description)),
+        )
+        .chain(std::iter::once((candidate.id.as_str(), weights.id)))
+        .filter(|&(_, weight)| weight > 0.0)
+        .filter_map(|(text, weight)| {
+            let score = matcher.score(text)?;
+            Some((score as f64 * weight).round() as i64)
+        })
+        .max()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fuzzy_match(pattern: &str, text: &str) -> Option<Match> {
+        let mut matcher = Matcher::default();
+        matcher.set_pattern(pattern);
+        matcher.fuzzy_match(text)
+    }
+
+    fn score(pattern: &str, text: &str) -> i64 {
+        fuzzy_match(pattern, text).unwrap().score
+    }
+
+    #[test]
+    fn word_starts_and_prefixes_rank_higher() {
+        assert!(score("fb", "foo bar") > score("fb", "afoobar"));
+        assert!(score("gi", "git status") > score("gi", "digit"));
+        assert!(score("ff", "FireFox") > score("ff", "diffing"));
+    }
+
+    #[test]
+    fn consecutive_matches_rank_higher() {
+        assert!(score("term", "terminal") > score("term", "the error mode"));
+    }
+
+    #[test]
+    fn no_match() {
+        assert_eq!(fuzzy_match("xyz", "firefox"), None);
+        assert_eq!(fuzzy_match("ba", "ab"), None);
+        assert_eq!(fuzzy_match("a", ""), None);
+    }
+
+    #[test]
+    fn empty_pattern_matches_everything() {
+        assert_eq!(
+            fuzzy_match("", "anything"),
+            Some(Match {
+                score: 0,
+                positions: Vec::new()
+            })
+        );
+    }
+
+    #[test]
+    fn case_folding_covers_non_ascii() {
+        assert!(fuzzy_match("ÜBER", "über").is_some());
+        assert!(fuzzy_match("straße", "STRASSE").is_none());
+        assert!(fuzzy_match("ελλ", "Ελλάδα").is_some());
+    }
+
+    #[test]
+    fn positions_are_char_indices() {
+        assert_eq!(fuzzy_match("fb", "foo bar").unwrap().positions, [0, 4]);
+        assert_eq!(fuzzy_match("äx", "bäx").unwrap().positions, [1, 2]);
+        // The word start wins over the earlier, plain occurrence.
+        assert_eq!(fuzzy_match("b", "abc bcd").unwrap().positions, [4]);
+    }
+
+    fn item(id: &str, label: &str, keywords: &[&str]) -> Item {
+        let mut item = Item::new(id, label);
+        item.keywords = keywords.iter().map(|keyword| keyword.to_string()).collect();
+        item
+    }
+
+    #[test]
+    fn label_hits_rank_above_keyword_and_id_hits() {
+        let items = [
+            item("browser", "Web", &["firefox"]),
+            item("firefox", "Browser", &[]),
+            item("web", "Firefox", &[]),
+            item("editor", "Editor", &[]),
+        ];
+        let mut state = FilterState::new(&items, SearchWeights::default());
+        state.set_query("firefox");
+
+        assert!(!state.is_visible(3));
+        let mut order: Vec<usize> = (0..3).filter(|&index| state.is_visible(index)).collect();
+        order.sort_by(|&a, &b| state.compare(a, b));
+        assert_eq!(order, [2, 0, 1]);
+        assert_eq!(state.best(), Some(2));
+
+        // Only label matches are highlighted.
+        assert_eq!(state.positions(2), [0, 1, 2, 3, 4, 5, 6]);
+        assert!(state.positions(0).is_empty());
+    }
+
+    #[test]
+    fn zero_weight_excludes_a_field() {
+        let items = [item("firefox", "Browser", &[])];
+        let weights = SearchWeights {
+            id: 0.0,
+            ..SearchWeights::default()
+        };
+        let mut state = FilterState::new(&items, weights);
+        state.set_query("firefox");
+        assert!(!state.is_visible(0));
+    }
+
+    #[test]
+    fn empty_query_keeps_input_order() {
+        let items = [item("b", "B", &[]), item("a", "A", &[])];
+        let mut state = FilterState::new(&items, SearchWeights::default());
+        state.set_query("");
+        assert!(state.is_visible(0) && state.is_visible(1));
+        assert_eq!(state.compare(0, 1), Ordering::Less);
+        assert_eq!(state.best(), Some(0));
+    }
+
+    #[test]
+    fn incremental_queries_match_a_fresh_filter() {
+        let items: Vec<Item> = ["firefox", "thunderbird", "files", "terminal", "foot"]
+            .iter()
+            .map(|name| item(name, name, &[]))
+            .collect();
+        let mut state = FilterState::new(&items, SearchWeights::default());
+        let mut fresh = FilterState::new(&items, SearchWeights::default());
+
+        for (query, change) in [
+            ("f", QueryChange::MoreStrict),
+            ("fi", QueryChange::MoreStrict),
+            ("fir", QueryChange::MoreStrict),
+            ("fi", QueryChange::LessStrict),
+            ("fo", QueryChange::Different),
+            ("", QueryChange::LessStrict),
+        ] {
+            assert_eq!(state.set_query(query), change, "{:?}", query);
+            fresh.set_query("");
+            fresh.set_query(query);
+            for index in 0..items.len() {
+                assert_eq!(
+                    state.is_visible(index),
+                    fresh.is_visible(index),
+                    "{:?}",
+                    query
+                );
+                assert_eq!(state.score(index), fresh.score(index), "{:?}", query);
+            }
+        }
+    }
+
+    /// Typing a query into a picker with 100k items, as the README promises
+    /// that stays fast. Takes a release build:
+    /// `cargo test --release -- --ignored filters_100k_items`
+    #[test]
+    #[ignore]
+    fn filters_100k_items() {
+        let words = [
+            "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf",
+        ];
+        let items: Vec<Item> = (0..100_000)
+            .map(|n| {
+                let label = format!(
+                    "{} {} {}",
+                    words[n % words.len()],
+                    words[n / 7 % words.len()],
+                    n
+                );
+                let mut item = item(&n.to_string(), &label, &["keyword"]);
+                item.description = Some(format!("Item number {}", n));
+                item
+            })
+            .collect();
+        let mut state = FilterState::new(&items, SearchWeights::default());
+
+        for query in [
+            "d", "de", "del", "delt", "delta", "delta ", "delta e", "delta ec",
+        ] {
+            let start = std::time::Instant::now();
+            state.set_query(query);
+            let elapsed = start.elapsed();
+            assert!(
+                elapsed < std::time::Duration::from_millis(50),
+                "{:?} took {:?}",
+                query,
+                elapsed
+            );
+        }
+        // "delta echo 31"
+        assert!(state.is_visible(31) && !state.is_visible(3));
+    }
+}
@@ -0,0 +1,138 @@
+use std::rc::Rc;
+
+use anyhow::{Context, Result};
+use gio::prelude::ApplicationExt;
+use gtk::Application;
+use gtk::prelude::*;
+
+use crate::core::load_css;
+use crate::data::{InputData, Item};
+use crate::frontend::{self, KeepOpen, Outcome};
+use crate::keys::KeyCombo;
+
+/// What the user did with the picker.
+#[derive(Debug, Clone)]
+pub enum Selection {
+    /// An item was chosen. Its action has not been run.
+    Chosen {
+        index: usize,
+        item: Box<Item>,
+        query: String,
+    },
+    /// Several items were marked and chosen, in the order they were marked.
+    Marked {
+        items: Vec<(usize, Item)>,
+        query: String,
+    },
+    /// An item was chosen with a key from its own or the picker's `bindings`.
+    Bound {
+        key: KeyCombo,
+        index: usize,
+        item: Box<Item>,
+        query: String,
+    },
+    /// A `custom-keys` entry was pressed. `number` is its position in the list,
+    /// `item` the highlighted item if any.
+    CustomKey {
+        number: usize,
+        item: Option<(usize, Box<Item>)>,
+        query: String,
+    },
+    /// The search text was submitted as is, with `custom-input` enabled.
+    Custom { query: String },
+    /// Enter was pressed while no item matched `query`.
+    NoMatch { query: String },
+    /// The picker was closed without a choice.
+    Cancelled,
+}
+
+impl Selection {
+    pub fn item(&self) -> Option<&Item> {
+        match self {
+            Selection::Chosen { item, .. } | Selection::Bound { item, .. } => Some(item),
+            Selection::CustomKey { item, .. } => item.as_ref().map(|(_, item)| &**item),
+            _ => None,
+        }
+    }
+}
+
+/// Shows a picker for an `InputData` and returns the user's [`Selection`].
+///
+/// ```no_run
+/// use pppicker::{Action, InputData, Item, Picker};
+///
+/// let hi = Item::new("hi", "Say hi").with_action(Action::Print { value: "hi".into() });
+/// let data = InputData::new("example", vec![hi]);
+/// if let Some(item) = Picker::new(data).run()?.item() {
+///     println!("{}", item.id);
+/// }
+/// # anyhow::Ok(())
+/// ```
+pub struct Picker {
+    data: InputData,
+    css: Option<String>,
+    keep_open: Option<KeepOpen>,
+}
+
+impl Picker {
+    pub fn new(data: InputData) -> Self {
+        Self {
+            data,
+            css: None,
+            keep_open: None,
+        }
+    }
+
+    /// Stylesheet to use instead of looking one up by the picker name.
+    pub fn css(mut self, css: impl Into<String>) -> Self {
+        self.css = Some(css.into());
+        self
+    }
+
+    /// Runs the action of a chosen `keep-open` or `reload` item while the
+    /// picker stays open. Returned items replace the shown ones, keeping the
+    /// query and selection. Without it, such items end the picker as usual.
+    pub fn keep_open(
+        mut self,
+        handler: impl Fn(&Selection) -> Option<Vec<Item>> + 'static,
+    ) -> Self {
+        self.keep_open = Some(Rc::new(handler));
+        self
+    }
+
+    /// Runs the GTK main loop until the picker closes.
+    pub fn run(self) -> Result<Selection> {
+        gtk::init().context("Failed to initialize GTK")?;
+
+        let provider = load_css(&self.data.name, self.css);
+
+        // Several pickers may run at once, from one process or many.
+        let app = Application::builder()
+            .application_id("dev.pppicker")
+            .flags(gio::ApplicationFlags::NON_UNIQUE)
+            .build();
+
+        let outcome = Outcome::default();
+        let outcome_ui = Rc::clone(&outcome);
+        let data = self.data;
+        let keep_open = self.keep_open;
+
+        app.connect_activate(move |app| {
+            frontend::run_ui(app, data.clone(), Rc::clone(&outcome_ui), keep_open.clone());
+        });
+
+        let status = app.run_with_args::<&str>(&[]);
+
+        if let Some(display) = gtk::gdk::Display::default() {
+            gtk::style_context_remove_provider_for_display(&display, &provider);
+        }
+
+        if status != glib::ExitCode::SUCCESS {
+            anyhow::bail!("GTK application exited with status {}", status.get());
+        }
+
+        // The main loop can also end without the window ever closing, e.g. when
+        // the application is quit from outside.
+        Ok(outcome.take().unwrap_or(Selection::Cancelled))
+    }
+}
@@ -0,0 +1,31 @@
+use std::collections::BTreeMap;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator};
+use serde_json::Value;
+
+use crate::data::{InputData, KeyAction};
+
+/// JSON Schema (draft 2020-12) of the picker input format, derived from the
+/// types in `data.rs`.
+pub fn schema() -> Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(InputData)).unwrap_or_default();
+    // Editors read `$schema` from the document itself; pppicker ignores it.
+    if let Some(properties) = schema["properties"].as_object_mut() {
+        properties.insert(
+            "$schema".to_string(),
+            serde_json::json!({
+                "description": "Schema this document follows, for editor support. Ignored by pppicker.",
+                "type": "string"
+            }),
+        );
+    }
+    schema
+}
+
+/// `keys` only takes the names of built-in commands.
+pub(crate) fn keys(generator: &mut SchemaGenerator) -> Schema {
+    let commands = generator.subschema_for::<KeyAction>();
+    let mut schema = <BTreeMap<String, Vec<String>>>::json_schema(generator);
+    schema.insert("propertyNames".to_string(), commands.into());
+    schema
+}
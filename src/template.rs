@@ -0,0 +1,233 @@
+use std::borrow::Cow;
+
+use crate::data::{Action, Exec, Item};
+
+/// Built-in placeholder names. Item `fields` with these names are shadowed.
+pub const BUILTIN_PLACEHOLDERS: [&str; 4] = ["id", "label", "query", "index"];
+
+/// Values for `{name}` placeholders in the action of a chosen item.
+#[derive(Clone, Copy)]
+pub struct Placeholders<'a> {
+    /// The chosen item and its index, `None` for custom input.
+    pub item: Option<(usize, &'a Item)>,
+    pub query: &'a str,
+}
+
+impl Placeholders<'_> {
+    fn value(&self, name: &str) -> Option<Cow<'_, str>> {
+        if name == "query" {
+            return Some(Cow::Borrowed(self.query));
+        }
+        let (index, item) = self.item?;
+        match name {
+            "id" => Some(Cow::Borrowed(&item.id)),
+            "label" => Some(Cow::Borrowed(&item.label)),
+            "index" => Some(Cow::Owned(index.to_string())),
+            _ => item
+                .fields
+                .get(name)
+                .map(|value| Cow::Borrowed(value.as_str())),
+        }
+    }
+
+    /// Replaces known placeholders. Anything else in braces, like `${HOME}`
+    /// in a shell command, is left alone.
+    pub fn expand(&self, template: &str) -> String {
+        self.expand_with(template, |value| value.to_string())
+    }
+
+    /// Like `expand`, but quotes each value as a single shell word.
+    pub fn expand_shell(&self, template: &str) -> String {
+        self.expand_with(template, shell_quote)
+    }
+
+    fn expand_with(&self, template: &str, escape: impl Fn(&str) -> String) -> String {
+        let mut expanded = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(open) = rest.find('{') {
+            expanded.push_str(&rest[..open]);
+            let after = &rest[open + 1..];
+            let value = after
+                .find('}')
+                .and_then(|close| Some((close, self.value(&after[..close])?)));
+            match value {
+                Some((close, value)) => {
+                    expanded.push_str(&escape(&value));
+                    rest = &after[close + 1..];
+                }
+                None => {
+                    expanded.push('{');
+                    rest = after;
+                }
+            }
+        }
+        expanded.push_str(rest);
+
+        expanded
+    }
+
+    /// The action with every placeholder expanded, shell-quoted in `cmd`.
+    pub fn apply(&self, action: &Action) -> Action {
+        match action {
+            Action::Exec(exec) => Action::Exec(Exec {
+                cmd: exec.cmd.as_deref().map(|cmd| self.expand_shell(cmd)),
+                args: exec
+                    .args
+                    .as_ref()
+                    .map(|args| args.iter().map(|arg| self.expand(arg)).collect()),
+                cwd: exec.cwd.as_ref().map(|cwd| match cwd.to_str() {
+                    Some(cwd) => self.expand(cwd).into(),
+                    None => cwd.clone(),
+                }),
+                env: exec
+                    .env
+                    .iter()
+                    .map(|(name, value)| (name.clone(), self.expand(value)))
+                    .collect(),
+                ..exec.clone()
+            }),
+            Action::Print { value } => Action::Print {
+                value: self.expand(value),
+            },
+            Action::Exit { code } => Action::Exit { code: *code },
+            Action::Reload { cmd } => Action::Reload {
+                cmd: cmd.as_deref().map(|cmd| self.expand_shell(cmd)),
+            },
+        }
+    }
+}
+
+fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@%+,".contains(c));
+    if plain {
+        value.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', r"'\''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn item() -> Item {
+        let mut item = Item::new("wall", "It's a wall");
+        item.fields = BTreeMap::from([
+            ("path".to_string(), "/tmp/my wall.png".to_string()),
+            ("empty".to_string(), String::new()),
+            ("id".to_string(), "shadowed".to_string()),
+        ]);
+        item
+    }
+
+    #[test]
+    fn expands_builtins_and_fields() {
+        let item = item();
+        let placeholders = Placeholders {
+            item: Some((3, &item)),
+            query: "wa",
+        };
+        assert_eq!(
+            placeholders.expand("{id} {label} {index} {query} {path}"),
+            "wall It's a wall 3 wa /tmp/my wall.png"
+        );
+    }
+
+    #[test]
+    fn builtins_shadow_fields() {
+        let item = item();
+        let placeholders = Placeholders {
+            item: Some((0, &item)),
+            query: "",
+        };
+        assert_eq!(placeholders.expand("{id}"), "wall");
+    }
+
+    #[test]
+    fn leaves_unknown_braces_alone() {
+        let item = item();
+        let placeholders = Placeholders {
+            item: Some((0, &item)),
+            query: "",
+        };
+        assert_eq!(
+            placeholders.expand("{unknown} ${HOME} {} {{id}}"),
+            "{unknown} ${HOME} {} {wall}"
+        );
+        assert_eq!(placeholders.expand("echo {id"), "echo {id");
+        assert_eq!(placeholders.expand("{ {id}"), "{ wall");
+    }
+
+    #[test]
+    fn custom_input_only_has_query() {
+        let placeholders = Placeholders {
+            item: None,
+            query: "text",
+        };
+        assert_eq!(placeholders.expand("{query} {id}"), "text {id}");
+    }
+
+    #[test]
+    fn quotes_shell_words() {
+        let item = item();
+        let placeholders = Placeholders {
+            item: Some((0, &item)),
+            query: "a;b",
+        };
+        assert_eq!(placeholders.expand_shell("echo {id}"), "echo wall");
+        assert_eq!(
+            placeholders.expand_shell("echo {label}"),
+            r"echo 'It'\''s a wall'"
+        );
+        assert_eq!(
+            placeholders.expand_shell("feh {path}"),
+            "feh '/tmp/my wall.png'"
+        );
+        assert_eq!(placeholders.expand_shell("x {empty} y"), "x '' y");
+        assert_eq!(placeholders.expand_shell("{query}"), "'a;b'");
+    }
+
+    #[test]
+    fn shell_quote_round_trips_through_sh() {
+        for value in ["", "plain", "it's", "''", "$HOME `id` \"x\"", "a\nb", "\\"] {
+            let output = std::process::Command::new("sh")
+                .arg("-c")
+                .arg(format!("printf %s {}", shell_quote(value)))
+                .output()
+                .unwrap();
+            assert_eq!(String::from_utf8(output.stdout).unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn apply_quotes_cmd_but_not_args() {
+        let item = item();
+        let placeholders = Placeholders {
+            item: Some((0, &item)),
+            query: "",
+        };
+        let action = Action::Exec(Exec {
+            cmd: Some("feh {path}".to_string()),
+            ..Default::default()
+        });
+        let Action::Exec(exec) = placeholders.apply(&action) else {
+            unreachable!();
+        };
+        assert_eq!(exec.cmd.as_deref(), Some("feh '/tmp/my wall.png'"));
+
+        let action = Action::Exec(Exec {
+            args: Some(vec!["feh".to_string(), "{path}".to_string()]),
+            ..Default::default()
+        });
+        let Action::Exec(exec) = placeholders.apply(&action) else {
+            unreachable!();
+        };
+        assert_eq!(exec.args.unwrap(), ["feh", "/tmp/my wall.png"]);
+    }
+}
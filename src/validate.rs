@@ -0,0 +1,1081 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use serde::Deserialize;
+use serde_json::Value;
+use serde_json::value::RawValue;
+
+use crate::core::EXIT_CUSTOM_KEY;
+use crate::data::{Action, ExecMode, Format, Icon, InputData, Item, OutputFormat};
+use crate::keys::{KeyCombo, check_keys};
+use crate::template::BUILTIN_PLACEHOLDERS;
+
+/// Custom keys exit with 10 to 19.
+pub(crate) const MAX_CUSTOM_KEYS: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: Option<Location>,
+    /// Index and id (when known) of the offending item.
+    pub item: Option<(usize, Option<String>)>,
+    pub message: String,
+}
+
+pub struct Report {
+    pub data: Option<InputData>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl Report {
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(location) = self.location {
+            write!(f, "{}:{}: ", location.line, location.column)?;
+        }
+        match self.severity {
+            Severity::Error => write!(f, "error: ")?,
+            Severity::Warning => write!(f, "warning: ")?,
+        }
+        match &self.item {
+            Some((index, Some(id))) => write!(f, "items[{}] (id \"{}\"): ", index, id)?,
+            Some((index, None)) => write!(f, "items[{}]: ", index)?,
+            None => {}
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Parses `input` and checks it for everything that would make the picker
+/// misbehave, collecting all problems instead of stopping at the first one.
+pub fn validate(input: &str, format: Format) -> Report {
+    run_checks(input, format, true)
+}
+
+/// Like [`validate`], without the checks that look past the parsed input:
+/// missing files and unknown keys. For input that is about to be shown.
+pub(crate) fn check(input: &str, format: Format) -> Report {
+    run_checks(input, format, false)
+}
+
+fn run_checks(input: &str, format: Format, thorough: bool) -> Report {
+    let parsed = match format {
+        Format::Json => serde_json::from_str::<InputData>(input).map_err(|err| json_error(&err)),
+        Format::Toml => toml::from_str::<InputData>(input).map_err(|err| toml_error(input, &err)),
+        Format::Dmenu => Ok(InputData::from_lines(input)),
+    };
+
+    let (data, mut diagnostics) = match parsed {
+        Ok(data) => {
+            let mut diagnostics = check_data(&data);
+            if thorough {
+                diagnostics.extend(check_files(&data));
+            }
+            (Some(data), diagnostics)
+        }
+        Err(diagnostic) => {
+            let mut diagnostics = match parse_value(input, format) {
+                Some(value) => check_values(&value),
+                None => Vec::new(),
+            };
+            if diagnostics.is_empty() {
+                diagnostics.push(diagnostic);
+            }
+            (None, diagnostics)
+        }
+    };
+    if thorough {
+        diagnostics.extend(check_unknown_keys(input, format));
+    }
+    locate_items(&mut diagnostics, input, format);
+
+    Report { data, diagnostics }
+}
+
+// Finding the items in the input takes another parse, so it only happens when
+// there is something to point at.
+fn locate_items(diagnostics: &mut [Diagnostic], input: &str, format: Format) {
+    if diagnostics
+        .iter()
+        .all(|diagnostic| diagnostic.item.is_none() || diagnostic.location.is_some())
+    {
+        return;
+    }
+    let offsets = item_offsets(input, format);
+    for diagnostic in diagnostics {
+        if diagnostic.location.is_none()
+            && let Some((index, _)) = diagnostic.item
+            && let Some(&offset) = offsets.get(index)
+        {
+            diagnostic.location = Some(location_at(input, offset));
+        }
+    }
+}
+
+fn json_error(err: &serde_json::Error) -> Diagnostic {
+    let message = err.to_string();
+    let suffix = format!(" at line {} column {}", err.line(), err.column());
+    let message = message.strip_suffix(&suffix).unwrap_or(&message);
+
+    Diagnostic {
+        severity: Severity::Error,
+        location: (err.line() > 0).then_some(Location {
+            line: err.line(),
+            column: err.column(),
+        }),
+        item: None,
+        message: humanize(message),
+    }
+}
+
+fn toml_error(input: &str, err: &toml::de::Error) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        location: err.span().map(|span| location_at(input, span.start)),
+        item: None,
+        message: humanize(err.message().trim()),
+    }
+}
+
+fn parse_value(input: &str, format: Format) -> Option<Value> {
+    match format {
+        Format::Json => serde_json::from_str(input).ok(),
+        Format::Toml => toml::from_str(input).ok(),
+        Format::Dmenu => None,
+    }
+}
+
+// Byte offsets of each entry of `items`, used to point diagnostics at the item.
+fn item_offsets(input: &str, format: Format) -> Vec<usize> {
+    #[derive(Deserialize)]
+    struct JsonItems<'a> {
+        #[serde(borrow)]
+        items: Vec<&'a RawValue>,
+    }
+
+    #[derive(Deserialize)]
+    struct TomlItems {
+        items: Vec<toml::Spanned<toml::Value>>,
+    }
+
+    match format {
+        Format::Json => serde_json::from_str::<JsonItems>(input)
+            .map(|raw| {
+                raw.items
+                    .iter()
+                    .map(|item| item.get().as_ptr() as usize - input.as_ptr() as usize)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        Format::Toml => toml::from_str::<TomlItems>(input)
+            .map(|raw| raw.items.iter().map(|item| item.span().start).collect())
+            .unwrap_or_default(),
+        Format::Dmenu => Vec::new(),
+    }
+}
+
+fn location_at(input: &str, offset: usize) -> Location {
+    let before = &input[..offset.min(input.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    Location {
+        line: before.matches('\n').count() + 1,
+        column: before[line_start..].chars().count() + 1,
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(usize),
+}
+
+// serde skips keys it does not know, so a misspelled key would silently fall
+// back to the default. Walks the input along the schema derived from the
+// types to find them.
+fn check_unknown_keys(input: &str, format: Format) -> Vec<Diagnostic> {
+    let Some(value) = parse_value(input, format) else {
+        return Vec::new();
+    };
+    let schema = crate::schema::schema();
+    let mut unknown = Vec::new();
+    find_unknown_keys(&schema, &schema, &value, &mut Vec::new(), &mut unknown);
+
+    unknown
+        .into_iter()
+        .map(|(path, suggestion)| {
+            let offset = match format {
+                Format::Json => json_key_offset(input, &path),
+                Format::Toml => toml_key_offset(input, &path),
+                Format::Dmenu => None,
+            };
+            let (item, field) = match path.as_slice() {
+                [Step::Key(items), Step::Index(index), rest @ ..] if items == "items" => {
+                    let id = value["items"][index]["id"].as_str().map(str::to_string);
+                    (Some((*index, id)), rest)
+                }
+                _ => (None, path.as_slice()),
+            };
+            let mut message = format!("unknown field \"{}\" is ignored", path_name(field));
+            if let Some(suggestion) = suggestion {
+                message.push_str(&format!(" (did you mean \"{}\"?)", suggestion));
+            }
+            Diagnostic {
+                severity: Severity::Warning,
+                location: offset.map(|offset| location_at(input, offset)),
+                item,
+                message,
+            }
+        })
+        .collect()
+}
+
+fn path_name(path: &[Step]) -> String {
+    let mut name = String::new();
+    for step in path {
+        match step {
+            Step::Key(key) if name.is_empty() => name.push_str(key),
+            Step::Key(key) => name.push_str(&format!(".{}", key)),
+            Step::Index(index) => name.push_str(&format!("[{}]", index)),
+        }
+    }
+    name
+}
+
+// Collects the path of every key that none of the schemas describing `value`
+// define, with the known key it is probably a misspelling of.
+fn find_unknown_keys(
+    root: &Value,
+    schema: &Value,
+    value: &Value,
+    path: &mut Vec<Step>,
+    unknown: &mut Vec<(Vec<Step>, Option<String>)>,
+) {
+    let mut shapes = Vec::new();
+    applicable_schemas(root, schema, value, &mut shapes);
+    let property = |key: &str| -> Vec<&Value> {
+        shapes
+            .iter()
+            .filter_map(|shape| shape.get("properties")?.get(key))
+            .collect()
+    };
+
+    match value {
+        Value::Object(object) => {
+            let has_properties = shapes.iter().any(|shape| shape.get("properties").is_some());
+            let additional = shapes
+                .iter()
+                .find_map(|shape| shape.get("additionalProperties"));
+            for (key, child) in object {
+                path.push(Step::Key(key.clone()));
+                let known = property(key);
+                if !known.is_empty() {
+                    for schema in known {
+                        find_unknown_keys(root, schema, child, path, unknown);
+                    }
+                } else if let Some(additional) = additional {
+                    find_unknown_keys(root, additional, child, path, unknown);
+                } else if has_properties && !unknown.iter().any(|(known, _)| known == path) {
+                    let kebab = key.replace('_', "-");
+                    let suggestion = (!property(&kebab).is_empty()).then_some(kebab);
+                    unknown.push((path.clone(), suggestion));
+                }
+                path.pop();
+            }
+        }
+        Value::Array(array) => {
+            for (index, child) in array.iter().enumerate() {
+                path.push(Step::Index(index));
+                for schema in shapes.iter().filter_map(|shape| shape.get("items")) {
+                    find_unknown_keys(root, schema, child, path, unknown);
+                }
+                path.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+// `schema` and the schemas it refers to or combines that `value` can match,
+// picking the branch of a tagged enum by its tag.
+fn applicable_schemas<'a>(
+    root: &'a Value,
+    schema: &'a Value,
+    value: &Value,
+    out: &mut Vec<&'a Value>,
+) {
+    if !schema_fits(schema, value) {
+        return;
+    }
+    out.push(schema);
+
+    if let Some(name) = schema
+        .get("$ref")
+        .and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix("#/$defs/"))
+        && let Some(definition) = root["$defs"].get(name)
+    {
+        applicable_schemas(root, definition, value, out);
+    }
+    for combinator in ["allOf", "anyOf", "oneOf"] {
+        for branch in schema
+            .get(combinator)
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            applicable_schemas(root, branch, value, out);
+        }
+    }
+}
+
+fn schema_fits(schema: &Value, value: &Value) -> bool {
+    let kind = match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    };
+    let type_fits = match schema.get("type") {
+        Some(Value::String(name)) => name == kind || (name == "integer" && kind == "number"),
+        Some(Value::Array(names)) => names
+            .iter()
+            .any(|name| name == kind || (name == "integer" && kind == "number")),
+        _ => true,
+    };
+    let const_fits = schema.get("const").is_none_or(|constant| constant == value);
+    let tags_fit = schema
+        .get("properties")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+        .all(
+            |(key, property)| match (property.get("const"), value.get(key)) {
+                (Some(constant), Some(tag)) => constant == tag,
+                _ => true,
+            },
+        );
+    type_fits && const_fits && tags_fit
+}
+
+fn json_key_offset(input: &str, path: &[Step]) -> Option<usize> {
+    let mut raw = input;
+    for step in path {
+        let child = match step {
+            Step::Key(key) => serde_json::from_str::<HashMap<String, &RawValue>>(raw)
+                .ok()?
+                .get(key)
+                .copied(),
+            Step::Index(index) => serde_json::from_str::<Vec<&RawValue>>(raw)
+                .ok()?
+                .get(*index)
+                .copied(),
+        };
+        raw = child?.get();
+    }
+    let offset = raw.as_ptr() as usize - input.as_ptr() as usize;
+
+    // Point at the key rather than at its value.
+    let key = input[..offset]
+        .trim_end()
+        .strip_suffix(':')
+        .map(str::trim_end)
+        .and_then(|before| before.strip_suffix('"'))
+        .and_then(|before| before.rfind('"'));
+    Some(key.unwrap_or(offset))
+}
+
+fn toml_key_offset(input: &str, path: &[Step]) -> Option<usize> {
+    let root = toml::de::DeTable::parse(input).ok()?;
+    let (last, parents) = path.split_last()?;
+
+    let mut table = root.get_ref();
+    let mut array: Option<&toml::de::DeArray> = None;
+    for step in parents {
+        let value = match (step, array) {
+            (Step::Index(index), Some(values)) => values.get(*index)?.get_ref(),
+            (Step::Key(key), None) => table
+                .iter()
+                .find(|(name, _)| **name.get_ref() == **key)?
+                .1
+                .get_ref(),
+            _ => return None,
+        };
+        match value {
+            toml::de::DeValue::Table(child) => {
+                table = child;
+                array = None;
+            }
+            toml::de::DeValue::Array(values) => array = Some(values),
+            _ => return None,
+        }
+    }
+
+    match (last, array) {
+        (Step::Key(key), None) => table
+            .iter()
+            .find(|(name, _)| **name.get_ref() == **key)
+            .map(|(name, _)| name.span().start),
+        _ => None,
+    }
+}
+
+fn check_values(value: &Value) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+
+    let mut picker_actions = vec![
+        ("default-action".to_string(), value.get("default-action")),
+        (
+            "custom-input".to_string(),
+            value
+                .get("custom-input")
+                .and_then(|custom| custom.get("action")),
+        ),
+    ];
+    picker_actions.extend(
+        bindings_value(value).map(|(key, action)| (format!("binding \"{}\"", key), Some(action))),
+    );
+    for (field, action) in picker_actions {
+        if let Some(action) = action.filter(|action| !action.is_null())
+            && let Err(err) = Action::deserialize(action)
+        {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                location: None,
+                item: None,
+                message: format!(
+                    "{}: {}",
+                    field,
+                    describe_field("action", "action", action, &err)
+                ),
+            });
+        }
+    }
+
+    let Some(items) = value.get("items").and_then(Value::as_array) else {
+        return diagnostics;
+    };
+
+    for (index, item) in items.iter().enumerate() {
+        let id = item.get("id").and_then(Value::as_str).map(str::to_string);
+        for message in check_item_value(item) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Error,
+                location: None,
+                item: Some((index, id.clone())),
+                message,
+            });
+        }
+    }
+    diagnostics
+}
+
+// Checks `action` and `icon` on their own first, so their errors can name the
+// action or icon type instead of serde's generic "unknown variant".
+fn check_item_value(item: &Value) -> Vec<String> {
+    let mut messages = Vec::new();
+
+    if let Some(action) = item.get("action").filter(|action| !action.is_null())
+        && let Err(err) = Action::deserialize(action)
+    {
+        messages.push(describe_field("action", "action", action, &err));
+    }
+
+    for (key, action) in bindings_value(item) {
+        if let Err(err) = Action::deserialize(action) {
+            messages.push(format!(
+                "binding \"{}\": {}",
+                key,
+                describe_field("action", "action", action, &err)
+            ));
+        }
+    }
+
+    if let Some(icon) = item.get("icon").filter(|icon| !icon.is_null())
+        && let Err(err) = Icon::deserialize(icon)
+    {
+        messages.push(describe_field("icon", "type", icon, &err));
+    }
+
+    if messages.is_empty()
+        && let Err(err) = Item::deserialize(item)
+    {
+        messages.push(humanize(&err.to_string()));
+    }
+
+    messages
+}
+
+fn bindings_value(value: &Value) -> impl Iterator<Item = (&String, &Value)> {
+    value
+        .get("bindings")
+        .and_then(Value::as_object)
+        .into_iter()
+        .flatten()
+}
+
+fn describe_field(field: &str, tag: &str, value: &Value, err: &serde_json::Error) -> String {
+    let message = err.to_string();
+    let kind = value.get(tag).and_then(Value::as_str);
+
+    if let Some(rest) = message.strip_prefix("unknown variant ") {
+        let (variant, expected) = rest.split_once(", expected ").unwrap_or((rest, ""));
+        return format!(
+            "unknown {} type \"{}\" (expected {})",
+            field,
+            variant.trim_matches('`'),
+            expected.replace('`', "")
+        );
+    }
+
+    if let Some(name) = missing_field(&message) {
+        return match kind {
+            Some(kind) if name != tag => format!("{} {} requires \"{}\"", kind, field, name),
+            _ => format!("{} is missing its \"{}\" type", field, tag),
+        };
+    }
+
+    format!("invalid {}: {}", field, humanize(&message))
+}
+
+fn missing_field(message: &str) -> Option<&str> {
+    message
+        .strip_prefix("missing field `")
+        .and_then(|rest| rest.strip_suffix('`'))
+}
+
+fn humanize(message: &str) -> String {
+    if let Some(name) = missing_field(message) {
+        return format!("missing required field \"{}\"", name);
+    }
+    message
+        .replace("unknown variant", "unknown value")
+        .replace('`', "\"")
+}
+
+fn check_data(data: &InputData) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut first_use: HashMap<&str, usize> = HashMap::new();
+
+    let picker_actions = [
+        ("default-action", data.default_action.as_ref()),
+        (
+            "custom-input",
+            data.custom_input.as_ref().map(|custom| &custom.action),
+        ),
+    ];
+    for (field, action) in picker_actions {
+        for (severity, message) in action.map(check_action).unwrap_or_default() {
+            diagnostics.push(Diagnostic {
+                severity,
+                location: None,
+                item: None,
+                message: format!("{}: {}", field, message),
+            });
+        }
+    }
+
+    for (key, action) in &data.bindings {
+        for (severity, message) in check_binding(key, action) {
+            diagnostics.push(Diagnostic {
+                severity,
+                location: None,
+                item: None,
+                message,
+            });
+        }
+    }
+
+    for (action, err) in check_keys(&data.keys) {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            location: None,
+            item: None,
+            message: format!("keys.{}: {}", action, err),
+        });
+    }
+
+    for (severity, message) in check_custom_keys(data) {
+        diagnostics.push(Diagnostic {
+            severity,
+            location: None,
+            item: None,
+            message,
+        });
+    }
+
+    if let Some(custom) = &data.custom_input
+        && let Action::Reload { .. } = custom.action
+    {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Error,
+            location: None,
+            item: None,
+            message: "custom-input: reload action only works on items".to_string(),
+        });
+    }
+
+    if data.custom_input.is_some() && data.search_bar.is_none() {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            location: None,
+            item: None,
+            message: "custom-input has no effect without a search-bar to type into".to_string(),
+        });
+    }
+
+    let template_format = data.output.format == OutputFormat::Template;
+    let output_problem = match (template_format, data.output.template.is_some()) {
+        (true, false) => Some((
+            Severity::Error,
+            "output format \"template\" needs a \"template\"",
+        )),
+        (false, true) => Some((
+            Severity::Warning,
+            "output template is ignored unless the format is \"template\"",
+        )),
+        _ => None,
+    };
+    if let Some((severity, message)) = output_problem {
+        diagnostics.push(Diagnostic {
+            severity,
+            location: None,
+            item: None,
+            message: message.to_string(),
+        });
+    }
+
+    if data.items.is_empty() {
+        diagnostics.push(Diagnostic {
+            severity: Severity::Warning,
+            location: None,
+            item: None,
+            message: "picker has no items".to_string(),
+        });
+    }
+
+    for (index, item) in data.items.iter().enumerate() {
+        let mut report = |severity, message: String| {
+            diagnostics.push(Diagnostic {
+                severity,
+                location: None,
+                item: Some((index, Some(item.id.clone()))),
+                message,
+            });
+        };
+
+        if let Some(&first) = first_use.get(item.id.as_str()) {
+            report(
+                Severity::Error,
+                format!("duplicate id (first used by items[{}])", first),
+            );
+        } else {
+            first_use.insert(&item.id, index);
+        }
+
+        match &item.action {
+            Some(action) => {
+                for (severity, message) in check_action(action) {
+                    report(severity, message);
+                }
+            }
+            None if data.default_action.is_none() => report(
+                Severity::Error,
+                "no \"action\" and the picker has no \"default-action\"".to_string(),
+            ),
+            None => {}
+        }
+
+        for (key, action) in &item.bindings {
+            for (severity, message) in check_binding(key, action) {
+                report(severity, message);
+            }
+        }
+
+        if item.keep_open
+            && let Some(Action::Exit { .. }) = item.action.as_ref().or(data.default_action.as_ref())
+        {
+            report(
+                Severity::Warning,
+                "exit action does nothing with \"keep-open\"".to_string(),
+            );
+        }
+
+        for name in item.fields.keys() {
+            if BUILTIN_PLACEHOLDERS.contains(&name.as_str()) {
+                report(
+                    Severity::Warning,
+                    format!(
+                        "field \"{}\" is shadowed by the built-in {{{}}} placeholder",
+                        name, name
+                    ),
+                );
+            }
+        }
+    }
+
+    diagnostics
+}
+
+fn check_files(data: &InputData) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut report = |severity, item, message| {
+        diagnostics.push(Diagnostic {
+            severity,
+            location: None,
+            item,
+            message,
+        });
+    };
+
+    let picker_actions = [
+        ("default-action: ".to_string(), data.default_action.as_ref()),
+        (
+            "custom-input: ".to_string(),
+            data.custom_input.as_ref().map(|custom| &custom.action),
+        ),
+    ]
+    .into_iter()
+    .chain(
+        data.bindings
+            .iter()
+            .map(|(key, action)| (format!("binding \"{}\": ", key), Some(action))),
+    );
+    for (prefix, action) in picker_actions {
+        if let Some(message) = action.and_then(check_cwd) {
+            report(Severity::Warning, None, format!("{}{}", prefix, message));
+        }
+    }
+
+    for (index, item) in data.items.iter().enumerate() {
+        let actions = item
+            .action
+            .iter()
+            .map(|action| (String::new(), action))
+            .chain(
+                item.bindings
+                    .iter()
+                    .map(|(key, action)| (format!("binding \"{}\": ", key), action)),
+            );
+        for (prefix, action) in actions {
+            if let Some(message) = check_cwd(action) {
+                report(
+                    Severity::Warning,
+                    Some((index, Some(item.id.clone()))),
+                    format!("{}{}", prefix, message),
+                );
+            }
+        }
+
+        if let Some(Icon::Path(path)) = &item.icon
+            && !path.exists()
+        {
+            report(
+                Severity::Error,
+                Some((index, Some(item.id.clone()))),
+                format!("icon file {} does not exist", path.display()),
+            );
+        }
+    }
+
+    diagnostics
+}
+
+fn check_cwd(action: &Action) -> Option<String> {
+    match action {
+        Action::Exec(exec) => exec
+            .cwd
+            .as_ref()
+            .filter(|cwd| !cwd.is_dir())
+            .map(|cwd| format!("exec cwd {} is not a directory", cwd.display())),
+        _ => None,
+    }
+}
+
+fn check_binding(key: &str, action: &Action) -> Vec<(Severity, String)> {
+    let mut problems = Vec::new();
+    if let Err(err) = key.parse::<KeyCombo>() {
+        problems.push((Severity::Error, format!("binding \"{}\": {}", key, err)));
+    }
+    if let Action::Reload { .. } = action {
+        problems.push((
+            Severity::Error,
+            format!(
+                "binding \"{}\": reload action only works as an item's \"action\"",
+                key
+            ),
+        ));
+    }
+    for (severity, message) in check_action(action) {
+        problems.push((severity, format!("binding \"{}\": {}", key, message)));
+    }
+    problems
+}
+
+fn check_custom_keys(data: &InputData) -> Vec<(Severity, String)> {
+    let mut problems = Vec::new();
+    if data.custom_keys.len() > MAX_CUSTOM_KEYS {
+        problems.push((
+            Severity::Error,
+            format!(
+                "custom-keys has {} keys, at most {} are supported (exit statuses {} to {})",
+                data.custom_keys.len(),
+                MAX_CUSTOM_KEYS,
+                EXIT_CUSTOM_KEY,
+                EXIT_CUSTOM_KEY + MAX_CUSTOM_KEYS as i32 - 1
+            ),
+        ));
+    }
+
+    let bound: Vec<KeyCombo> = data
+        .bindings
+        .keys()
+        .chain(data.items.iter().flat_map(|item| item.bindings.keys()))
+        .filter_map(|key| key.parse().ok())
+        .collect();
+    for (index, key) in data.custom_keys.iter().enumerate() {
+        match key.parse::<KeyCombo>() {
+            Ok(combo) if bound.contains(&combo) => problems.push((
+                Severity::Warning,
+                format!(
+                    "custom-keys[{}]: \"{}\" is also in bindings, which it overrides",
+                    index, key
+                ),
+            )),
+            Ok(_) => {}
+            Err(err) => {
+                problems.push((Severity::Error, format!("custom-keys[{}]: {}", index, err)))
+            }
+        }
+    }
+    problems
+}
+
+fn check_action(action: &Action) -> Vec<(Severity, String)> {
+    let mut problems = Vec::new();
+    let exec = match action {
+        Action::Exec(exec) => exec,
+        Action::Reload { cmd: Some(cmd) } if cmd.trim().is_empty() => {
+            problems.push((
+                Severity::Error,
+                "reload action has an empty \"cmd\"".to_string(),
+            ));
+            return problems;
+        }
+        _ => return problems,
+    };
+
+    let problem = match (&exec.cmd, &exec.args) {
+        (Some(_), Some(_)) => Some("exec action has both \"cmd\" and \"args\" (use one)"),
+        (None, None) => Some("exec action requires \"cmd\" or \"args\""),
+        (Some(cmd), None) if cmd.trim().is_empty() => Some("exec action has an empty \"cmd\""),
+        (None, Some(args)) if args.first().is_none_or(|program| program.is_empty()) => {
+            Some("exec action has no program in \"args\"")
+        }
+        _ => None,
+    };
+    if let Some(problem) = problem {
+        problems.push((Severity::Error, problem.to_string()));
+    }
+
+    if let Some(timeout) = exec.timeout
+        && !(timeout.is_finite() && timeout > 0.0)
+    {
+        problems.push((
+            Severity::Error,
+            format!(
+                "exec timeout must be a positive number of seconds, got {}",
+                timeout
+            ),
+        ));
+    }
+
+    if exec.mode == ExecMode::Detach {
+        for (set, field) in [
+            (exec.timeout.is_some(), "timeout"),
+            (exec.forward_stdout, "forward-stdout"),
+        ] {
+            if set {
+                problems.push((
+                    Severity::Warning,
+                    format!("exec \"{}\" only applies to \"mode\": \"wait\"", field),
+                ));
+            }
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(input: &str, format: Format) -> Vec<String> {
+        validate(input, format)
+            .diagnostics
+            .iter()
+            .map(Diagnostic::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn valid_input_has_no_diagnostics() {
+        let input = r#"{"name": "x", "items": [{"id": "a", "label": "A", "action": {"action": "print", "value": "a"}}]}"#;
+        let report = validate(input, Format::Json);
+        assert!(report.diagnostics.is_empty());
+        assert!(!report.has_errors());
+        assert_eq!(report.data.unwrap().items.len(), 1);
+    }
+
+    #[test]
+    fn duplicate_id() {
+        let input = r#"{"name": "x", "default-action": {"action": "print", "value": "{id}"},
+"items": [
+  {"id": "a", "label": "A"},
+  {"id": "a", "label": "B"}
+]}"#;
+        assert_eq!(
+            messages(input, Format::Json),
+            ["4:3: error: items[1] (id \"a\"): duplicate id (first used by items[0])"]
+        );
+    }
+
+    #[test]
+    fn exec_without_cmd() {
+        let input = "name = \"x\"\n\n[[items]]\nid = \"a\"\nlabel = \"A\"\naction = { action = \"exec\" }\n";
+        let report = validate(input, Format::Toml);
+        assert!(report.has_errors());
+        assert_eq!(
+            report.diagnostics[0].to_string(),
+            "3:1: error: items[0] (id \"a\"): exec action requires \"cmd\" or \"args\""
+        );
+    }
+
+    #[test]
+    fn unknown_action_type() {
+        let input = "{\n  \"name\": \"x\",\n  \"items\": [\n    {\"id\": \"a\", \"label\": \"A\", \"action\": {\"action\": \"launch\"}}\n  ]\n}";
+        let report = validate(input, Format::Json);
+        assert!(report.data.is_none());
+        assert_eq!(
+            report.diagnostics[0].to_string(),
+            "4:5: error: items[0] (id \"a\"): unknown action type \"launch\" (expected one of exec, print, exit, reload)"
+        );
+    }
+
+    #[test]
+    fn syntax_errors_have_locations() {
+        assert_eq!(
+            messages("{\"name\": \"x\",\n \"items\": [,]}", Format::Json),
+            ["2:12: error: expected value"]
+        );
+        let report = validate("name = \"x\"\nitems = [\n", Format::Toml);
+        assert!(report.data.is_none());
+        assert!(
+            report.diagnostics[0]
+                .location
+                .is_some_and(|location| location.line == 3)
+        );
+    }
+
+    #[test]
+    fn missing_field() {
+        assert_eq!(
+            messages(r#"{"name": "x", "itemz": []}"#, Format::Json),
+            [
+                "1:26: error: missing required field \"items\"",
+                "1:15: warning: unknown field \"itemz\" is ignored"
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_keys_are_warnings() {
+        let input = r#"{
+  "$schema": "input.schema.json",
+  "name": "x",
+  "search_bar": {"placeholder": "Search"},
+  "default-action": {"action": "exec", "cmd": "true", "forward_stdout": true},
+  "items": [
+    {"id": "a", "label": "A", "keep_open": true, "fields": {"any": "thing"}}
+  ]
+}"#;
+        let report = validate(input, Format::Json);
+        assert!(!report.has_errors());
+        assert_eq!(
+            report
+                .diagnostics
+                .iter()
+                .map(Diagnostic::to_string)
+                .collect::<Vec<_>>(),
+            [
+                "5:55: warning: unknown field \"default-action.forward_stdout\" is ignored (did you mean \"forward-stdout\"?)",
+                "7:31: warning: items[0] (id \"a\"): unknown field \"keep_open\" is ignored (did you mean \"keep-open\"?)",
+                "4:3: warning: unknown field \"search_bar\" is ignored (did you mean \"search-bar\"?)"
+            ]
+        );
+
+        let input = "name = \"x\"\ndefault_action = { action = \"print\", value = \"{id}\" }\n\n[[items]]\nid = \"a\"\nlabel = \"A\"\n\n[items.icon]\ntype = \"unicode\"\nvalue = \"a\"\nsize = 2\n";
+        assert_eq!(
+            messages(input, Format::Toml),
+            [
+                "4:1: error: items[0] (id \"a\"): no \"action\" and the picker has no \"default-action\"",
+                "2:1: warning: unknown field \"default_action\" is ignored (did you mean \"default-action\"?)",
+                "11:1: warning: items[0] (id \"a\"): unknown field \"icon.size\" is ignored"
+            ]
+        );
+    }
+
+    #[test]
+    fn missing_icon_file() {
+        let input = r#"{"name": "x", "default-action": {"action": "print", "value": "{id}"},
+"items": [{"id": "a", "label": "A", "icon": {"type": "path", "value": "/nonexistent/a.png"}}]}"#;
+        let report = validate(input, Format::Json);
+        assert!(report.has_errors());
+        assert_eq!(
+            report.diagnostics[0].to_string(),
+            "2:11: error: items[0] (id \"a\"): icon file /nonexistent/a.png does not exist"
+        );
+    }
+
+    #[test]
+    fn check_leaves_out_files_and_unknown_keys() {
+        let input = r#"{"name": "x", "default-action": {"action": "exec", "cmd": "true", "cwd": "/nonexistent"},
+"items": [{"id": "a", "label": "A", "keep_open": true, "icon": {"type": "path", "value": "/nonexistent/a.png"}}]}"#;
+        assert_eq!(validate(input, Format::Json).diagnostics.len(), 3);
+        let report = check(input, Format::Json);
+        assert!(report.diagnostics.is_empty());
+        assert!(report.data.is_some());
+    }
+
+    #[test]
+    fn warnings_without_location() {
+        let report = validate(r#"{"name": "x", "items": []}"#, Format::Json);
+        assert!(!report.has_errors());
+        assert_eq!(report.diagnostics[0].location, None);
+        assert_eq!(
+            report.diagnostics[0].to_string(),
+            "warning: picker has no items"
+        );
+    }
+}
@@ -0,0 +1,329 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+use pppicker::{
+    Action, CustomInput, Exec, ExecMode, ExitCodes, Icon, InputData, Item, KeyAction, Layout,
+    MarkedOutput, MultiSelect, Output, OutputFormat, SearchBar, SearchWeights, Separator, View,
+    schema,
+};
+use serde_json::Value;
+
+// Exhaustive on purpose: a new variant does not compile until it is listed
+// here, and `schema_covers_every_variant` checks the schema has as many.
+fn all_actions() -> Vec<Action> {
+    let actions = vec![
+        Action::Exec(Exec {
+            cmd: None,
+            args: Some(vec!["notify-send".to_string(), "hi".to_string()]),
+            cwd: Some(PathBuf::from("/tmp")),
+            env: BTreeMap::from([("LANG".to_string(), "C".to_string())]),
+            clear_env: true,
+            mode: ExecMode::Wait,
+            timeout: Some(2.5),
+            forward_stdout: true,
+        }),
+        Action::Print {
+            value: "value".to_string(),
+        },
+        Action::Exit { code: 3 },
+        Action::Reload {
+            cmd: Some("ls ~/Pictures".to_string()),
+        },
+    ];
+    for action in &actions {
+        match action {
+            Action::Exec(_)
+            | Action::Print { .. }
+            | Action::Exit { .. }
+            | Action::Reload { .. } => {}
+        }
+    }
+    actions
+}
+
+fn all_icons() -> Vec<Icon> {
+    let icons = vec![
+        Icon::Unicode("🎨".to_string()),
+        Icon::Path(PathBuf::from("/tmp/icon.png")),
+    ];
+    for icon in &icons {
+        match icon {
+            Icon::Unicode(_) | Icon::Path(_) => {}
+        }
+    }
+    icons
+}
+
+fn all_key_actions() -> Vec<KeyAction> {
+    let actions = vec![
+        KeyAction::Accept,
+        KeyAction::SubmitQuery,
+        KeyAction::Cancel,
+        KeyAction::ClearQuery,
+        KeyAction::DeleteChar,
+        KeyAction::DeleteWord,
+        KeyAction::ToggleMark,
+        KeyAction::Up,
+        KeyAction::Down,
+        KeyAction::Left,
+        KeyAction::Right,
+        KeyAction::PageUp,
+        KeyAction::PageDown,
+        KeyAction::First,
+        KeyAction::Last,
+    ];
+    for action in &actions {
+        match action {
+            KeyAction::Accept
+            | KeyAction::SubmitQuery
+            | KeyAction::Cancel
+            | KeyAction::ClearQuery
+            | KeyAction::DeleteChar
+            | KeyAction::DeleteWord
+            | KeyAction::ToggleMark
+            | KeyAction::Up
+            | KeyAction::Down
+            | KeyAction::Left
+            | KeyAction::Right
+            | KeyAction::PageUp
+            | KeyAction::PageDown
+            | KeyAction::First
+            | KeyAction::Last => {}
+        }
+    }
+    actions
+}
+
+// Every field is set, so every property the types can produce shows up.
+fn full_input() -> InputData {
+    let icons = all_icons();
+    let items = all_actions()
+        .into_iter()
+        .enumerate()
+        .map(|(index, action)| Item {
+            id: format!("item-{}", index),
+            label: format!("Item {}", index),
+            description: Some("description".to_string()),
+            keywords: vec!["keyword".to_string()],
+            icon: Some(icons[index % icons.len()].clone()),
+            fields: BTreeMap::from([("path".to_string(), "/tmp/a.png".to_string())]),
+            action: Some(action),
+            keep_open: true,
+            bindings: BTreeMap::from([(
+                "alt+Return".to_string(),
+                Action::Print {
+                    value: "{id}".to_string(),
+                },
+            )]),
+        })
+        .collect();
+
+    InputData {
+        name: "schema".to_string(),
+        items,
+        view: View {
+            layout: Layout::Grid,
+            hide_description: true,
+        },
+        search_bar: Some(SearchBar {
+            placeholder: "Search...".to_string(),
+            highlight: Some("weight=\"bold\"".to_string()),
+            weights: SearchWeights {
+                label: 1.0,
+                keywords: 0.5,
+                description: 0.25,
+                id: 0.0,
+            },
+        }),
+        exit_codes: ExitCodes {
+            cancel: 130,
+            no_match: 4,
+        },
+        default_action: Some(Action::Print {
+            value: "{label}".to_string(),
+        }),
+        custom_input: Some(CustomInput {
+            action: Action::Print {
+                value: "{query}".to_string(),
+            },
+        }),
+        multi_select: Some(MultiSelect {
+            output: MarkedOutput::Json,
+        }),
+        output: Output {
+            format: OutputFormat::Template,
+            template: Some("{id}\t{path}".to_string()),
+            separator: Separator::Nul,
+        },
+        bindings: BTreeMap::from([("ctrl+Return".to_string(), Action::Exit { code: 5 })]),
+        custom_keys: vec!["alt+1".to_string()],
+        keys: all_key_actions()
+            .into_iter()
+            .map(|action| (action, vec!["ctrl+j".to_string()]))
+            .collect(),
+    }
+}
+
+fn validator() -> jsonschema::Validator {
+    jsonschema::validator_for(&schema()).expect("schema is not a valid JSON Schema")
+}
+
+fn errors(validator: &jsonschema::Validator, value: &Value) -> Vec<String> {
+    validator
+        .iter_errors(value)
+        .map(|err| format!("{}: {}", err.instance_path(), err))
+        .collect()
+}
+
+/// Names a `$defs` entry accepts: `const` and `enum` values of its branches.
+fn variants(definition: &Value) -> Vec<&Value> {
+    let branches = match definition.get("oneOf").and_then(Value::as_array) {
+        Some(branches) => branches.iter().collect(),
+        None => vec![definition],
+    };
+    branches
+        .into_iter()
+        .flat_map(|branch| {
+            let constant = branch
+                .get("const")
+                .or_else(|| branch.pointer("/properties/action/const"))
+                .or_else(|| branch.pointer("/properties/type/const"));
+            let values = branch.get("enum").and_then(Value::as_array);
+            constant.into_iter().chain(values.into_iter().flatten())
+        })
+        .collect()
+}
+
+#[test]
+fn schema_matches_types() {
+    let value = serde_json::to_value(full_input()).unwrap();
+    let errors = errors(&validator(), &value);
+    assert!(errors.is_empty(), "schema and types disagree: {:?}", errors);
+}
+
+#[test]
+fn schema_covers_every_variant() {
+    let schema = schema();
+    let definition = |name: &str| &schema["$defs"][name];
+
+    assert_eq!(variants(definition("Action")).len(), all_actions().len());
+    assert_eq!(variants(definition("Icon")).len(), all_icons().len());
+    assert_eq!(
+        variants(definition("KeyAction")).len(),
+        all_key_actions().len()
+    );
+}
+
+#[test]
+fn schema_defaults_match_types() {
+    let schema = schema();
+    let default = |pointer: &str| schema.pointer(pointer).unwrap().clone();
+
+    assert_eq!(
+        default("/properties/exit-codes/default"),
+        serde_json::to_value(ExitCodes::default()).unwrap()
+    );
+    assert_eq!(
+        default("/$defs/SearchBar/properties/weights/default"),
+        serde_json::to_value(SearchWeights::default()).unwrap()
+    );
+    assert_eq!(
+        default("/properties/view/default"),
+        serde_json::to_value(View::default()).unwrap()
+    );
+    assert_eq!(schema["required"], serde_json::json!(["name", "items"]));
+}
+
+// The parser takes a document exactly when the schema does. Unknown keys are
+// accepted by both; `pppicker validate` warns about them.
+#[test]
+fn schema_and_parser_agree() {
+    let validator = validator();
+    let cases = [
+        (r#"{"name": "x", "items": []}"#, true),
+        (
+            r#"{"name": "x", "items": [], "view": {"hide-description": true}}"#,
+            true,
+        ),
+        (
+            r#"{"name": "x", "items": [{"id": "a", "label": "A"}]}"#,
+            true,
+        ),
+        (
+            r#"{"$schema": "input.schema.json", "name": "x", "items": []}"#,
+            true,
+        ),
+        (
+            r#"{"name": "x", "items": [], "search_bar": {"placeholder": "x"}}"#,
+            true,
+        ),
+        (
+            r#"{"name": "x", "items": [{"id": "a", "label": "A", "keep_open": true}]}"#,
+            true,
+        ),
+        (r#"{"name": "x"}"#, false),
+        (r#"{"name": "x", "items": [{"id": "a"}]}"#, false),
+        (
+            r#"{"name": "x", "items": [], "view": {"layout": "grdi"}}"#,
+            false,
+        ),
+        (
+            r#"{"name": "x", "items": [], "keys": {"acept": ["Return"]}}"#,
+            false,
+        ),
+        (
+            r#"{"name": "x", "items": [], "exit-codes": {"cancel": "1"}}"#,
+            false,
+        ),
+        (
+            r#"{"name": "x", "items": [], "default-action": {"action": "prnt", "value": "a"}}"#,
+            false,
+        ),
+        (
+            r#"{"name": "x", "items": [], "default-action": {"action": "print"}}"#,
+            false,
+        ),
+        (
+            r#"{"name": "x", "items": [{"id": "a", "label": "A", "icon": {"type": "svg", "value": "a.svg"}}]}"#,
+            false,
+        ),
+    ];
+
+    for (document, valid) in cases {
+        let value: Value = serde_json::from_str(document).unwrap();
+        assert_eq!(validator.is_valid(&value), valid, "schema on {}", document);
+        assert_eq!(
+            serde_json::from_str::<InputData>(document).is_ok(),
+            valid,
+            "parser on {}",
+            document
+        );
+    }
+}
+
+#[test]
+fn examples_match_schema() {
+    let validator = validator();
+    let examples = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("examples");
+
+    for name in [
+        "example-list.json",
+        "example-grid.json",
+        "example-power.toml",
+    ] {
+        let content = std::fs::read_to_string(examples.join(name)).unwrap();
+        let value: Value = if name.ends_with(".toml") {
+            toml::from_str(&content).unwrap()
+        } else {
+            serde_json::from_str(&content).unwrap()
+        };
+
+        let errors = errors(&validator, &value);
+        assert!(
+            errors.is_empty(),
+            "{} does not match the schema: {:?}",
+            name,
+            errors
+        );
+    }
+}